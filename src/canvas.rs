@@ -0,0 +1,38 @@
+use sdl2::pixels;
+use sdl2::rect;
+use sdl2::render::{Canvas, RenderTarget};
+
+use tetris::drawing::{Color, Rect, Surface};
+
+// Lets the game drawing code draw onto an SDL canvas.
+pub struct CanvasSurface<'a, T: RenderTarget + 'a>(pub &'a mut Canvas<T>);
+
+fn sdl_rect(r: Rect) -> rect::Rect {
+    rect::Rect::new(r.x, r.y, r.w, r.h)
+}
+
+impl<'a, T: RenderTarget> Surface for CanvasSurface<'a, T> {
+    fn set_draw_color(&mut self, c: Color) {
+        self.0.set_draw_color(pixels::Color::RGBA(c.r, c.g, c.b, c.a));
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32)) -> Result<(), String> {
+        self.0.draw_line(from, to)
+    }
+
+    fn draw_rect(&mut self, r: Rect) -> Result<(), String> {
+        self.0.draw_rect(sdl_rect(r))
+    }
+
+    fn fill_rect(&mut self, r: Rect) -> Result<(), String> {
+        self.0.fill_rect(sdl_rect(r))
+    }
+
+    fn present(&mut self) {
+        self.0.present();
+    }
+}
@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use tetris::game::repeat::Rates;
+use tetris::game::tetris::lock::{LockReset, LOCK_RESETS};
+use tetris::game::tetris::randomizer::{Randomizer, RANDOMIZERS};
+use tetris::game::tetris::rotation::{Rotation, ROTATIONS};
+
+pub const USAGE: &str = "usage: tetris [OPTIONS] [REPLAY]
+
+options:
+    --game NAME          start on the named game
+    --level N            starting level, from 1
+    --height N           rows of rubbish to start tetris with
+    --rotation NAME      turn tetris pieces the nintendo or super way
+    --hold               let tetris pieces be put aside for later
+    --ghost              show where each tetris piece would land
+    --previews N         how many of the pieces to come tetris shows, from 1
+    --randomizer NAME    deal tetris pieces at random, or by bag, nes or tgm
+    --lock NAME          give landed tetris pieces time by classic, step,
+                         move or infinite reset
+    --are N              ticks before the next tetris piece comes in
+    --field N            which field to play snake on, from 1
+    --robots N           how many robots to start with
+    --das MS             how long left or right is held before it repeats
+    --arr MS             how long between repeats, or 0 to go all the way
+    --soft-drop MS       how long down takes over each row, or 0 for all
+    --seed N             seed the random numbers with N
+    --size WxH           open a window W pixels wide and H high
+    --fullscreen         fill the screen
+    --data DIR           keep scores, saved games and settings in DIR
+    --replay FILE        play back a replay, as does giving it alone
+    --screenshot FILE    draw the game to FILE instead of opening a window
+    --print-events       print what happens in the game as it is played
+    --help               show this message";
+
+// The smallest window the games can be drawn in.
+pub const MIN_SIZE: (u32, u32) = (128, 168);
+
+// Everything that can be chosen on the command line.
+pub struct Args {
+    pub game: Option<String>,
+    // settings for the games' menus, by the names the games know them by.
+    pub options: Vec<(&'static str, u32)>,
+    // how held directions repeat, in every game.
+    pub rates: Rates,
+    // show the ghost piece, whatever the player chose last time.
+    pub ghost: bool,
+    pub seed: Option<u64>,
+    pub size: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub data: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub screenshot: Option<PathBuf>,
+    pub print_events: bool,
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+        let mut it = Args {
+            game: None,
+            options: Vec::new(),
+            rates: Rates::default(),
+            ghost: false,
+            seed: None,
+            size: None,
+            fullscreen: false,
+            data: None,
+            replay: None,
+            screenshot: None,
+            print_events: false,
+            help: false,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--game" => it.game = Some(value(&arg, &mut args)?),
+                "--level" => it.options.push(("level", number(&arg, &mut args)?)),
+                "--height" => it.options.push(("height", number(&arg, &mut args)?)),
+                "--rotation" => it.options.push(("rotation", rotation(&value(&arg, &mut args)?)?)),
+                "--hold" => it.options.push(("hold", 1)),
+                "--ghost" => it.ghost = true,
+                "--previews" => it.options.push(("previews", number(&arg, &mut args)?)),
+                "--randomizer" => {
+                    it.options.push(("randomizer", randomizer(&value(&arg, &mut args)?)?))
+                }
+                "--lock" => it.options.push(("lock", lock(&value(&arg, &mut args)?)?)),
+                "--are" => it.options.push(("are", number(&arg, &mut args)?)),
+                "--field" => it.options.push(("field", number(&arg, &mut args)?)),
+                "--robots" => it.options.push(("robots", number(&arg, &mut args)?)),
+                "--das" => it.rates.das = number(&arg, &mut args)?,
+                "--arr" => it.rates.arr = number(&arg, &mut args)?,
+                "--soft-drop" => it.rates.soft_drop = number(&arg, &mut args)?,
+                "--seed" => it.seed = Some(number(&arg, &mut args)?),
+                "--size" => it.size = Some(size(&value(&arg, &mut args)?)?),
+                "--fullscreen" => it.fullscreen = true,
+                "--data" => it.data = Some(value(&arg, &mut args)?.into()),
+                "--replay" => it.replay = Some(value(&arg, &mut args)?.into()),
+                "--screenshot" => it.screenshot = Some(value(&arg, &mut args)?.into()),
+                "--print-events" => it.print_events = true,
+                "--help" | "-h" => it.help = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ => it.replay = Some(arg.into()),
+            }
+        }
+        Ok(it)
+    }
+}
+
+fn value<I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn number<T: FromStr, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<T, String> {
+    let v = value(flag, args)?;
+    v.parse().map_err(|_| format!("{} needs a number, not {}", flag, v))
+}
+
+// the rotation system's number, for the game's settings.
+fn rotation(v: &str) -> Result<u32, String> {
+    match Rotation::from_name(v) {
+        Some(r) => Ok(r as u32),
+        None => {
+            let names: Vec<&str> = ROTATIONS.iter().map(|r| r.name()).collect();
+            Err(format!("--rotation needs one of {}, not {}", names.join(", "), v))
+        }
+    }
+}
+
+fn randomizer(v: &str) -> Result<u32, String> {
+    match Randomizer::from_name(v) {
+        Some(r) => Ok(r as u32),
+        None => {
+            let names: Vec<&str> = RANDOMIZERS.iter().map(|r| r.name()).collect();
+            Err(format!("--randomizer needs one of {}, not {}", names.join(", "), v))
+        }
+    }
+}
+
+fn lock(v: &str) -> Result<u32, String> {
+    match LockReset::from_name(v) {
+        Some(r) => Ok(r as u32),
+        None => {
+            let names: Vec<&str> = LOCK_RESETS.iter().map(|r| r.name()).collect();
+            Err(format!("--lock needs one of {}, not {}", names.join(", "), v))
+        }
+    }
+}
+
+fn size(v: &str) -> Result<(u32, u32), String> {
+    let bad = || format!("--size needs a size such as 248x328, not {}", v);
+    let mut parts = v.splitn(2, 'x');
+    let w = parts.next().and_then(|w| w.parse().ok()).ok_or_else(bad)?;
+    let h = parts.next().and_then(|h| h.parse().ok()).ok_or_else(bad)?;
+    if w < MIN_SIZE.0 || h < MIN_SIZE.1 {
+        return Err(format!("--size must be at least {}x{}", MIN_SIZE.0, MIN_SIZE.1));
+    }
+    Ok((w, h))
+}
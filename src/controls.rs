@@ -0,0 +1,176 @@
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::GameControllerSubsystem;
+
+use std::io::BufRead;
+
+use tetris::drawing::Preferences;
+use tetris::game::{Action, ACTIONS};
+use tetris::game::repeat::{AutoRepeat, Rates};
+use tetris::storage::Storage;
+
+use keys::Bindings;
+
+// How far a stick has to be pushed before it counts as a direction.
+const STICK_THRESHOLD: i16 = 16384;
+
+// Turns keyboard and game controller events into presses and releases of
+// actions. Controllers are opened as they are plugged in; SDL reports the
+// ones already connected at start up the same way.
+pub struct Controls<'a> {
+    pub keys: Bindings<'a, Keycode>,
+    pub buttons: Bindings<'a, Button>,
+    subsystem: GameControllerSubsystem,
+    pads: Vec<GameController>,
+    // the direction each stick axis is pushed in, by controller.
+    sticks: Vec<(i32, Axis, Option<Action>)>,
+    // turns the directions held into moves, once a tick.
+    pub repeat: AutoRepeat,
+    // how the player likes the games drawn, kept alongside the bindings.
+    pub prefs: Preferences,
+    storage: &'a dyn Storage,
+    prefs_key: &'static str,
+}
+
+impl<'a> Controls<'a> {
+    pub fn new(
+        subsystem: GameControllerSubsystem,
+        storage: &'a dyn Storage,
+        keys_key: &'static str,
+        buttons_key: &'static str,
+        prefs_key: &'static str,
+        rates: Rates,
+    ) -> Controls<'a> {
+        Controls {
+            keys: load(storage, keys_key),
+            buttons: load(storage, buttons_key),
+            subsystem,
+            pads: Vec::new(),
+            sticks: Vec::new(),
+            repeat: AutoRepeat::new(rates),
+            prefs: load_prefs(storage, prefs_key),
+            storage,
+            prefs_key,
+        }
+    }
+
+    // show or hide the ghost in every game, and remember it for next time.
+    pub fn toggle_ghost(&mut self) {
+        self.prefs.ghost = !self.prefs.ghost;
+        let text = format!("ghost = {}\n", if self.prefs.ghost { "on" } else { "off" });
+        if let Err(e) = self.storage.write(self.prefs_key, text.as_bytes()) {
+            eprintln!("could not save settings to {}: {}", self.prefs_key, e);
+        }
+    }
+
+    pub fn actions(&mut self, event: &Event) -> Vec<(Action, bool)> {
+        match *event {
+            Event::KeyDown { keycode: Some(k), .. } => pressed(self.keys.action(k), true),
+            Event::KeyUp { keycode: Some(k), .. } => pressed(self.keys.action(k), false),
+            Event::ControllerButtonDown { button, .. } => pressed(self.buttons.action(button), true),
+            Event::ControllerButtonUp { button, .. } => pressed(self.buttons.action(button), false),
+            Event::ControllerAxisMotion { which, axis, value, .. } => self.stick(which, axis, value),
+            Event::ControllerDeviceAdded { which, .. } => {
+                match self.subsystem.open(which as u32) {
+                    Ok(pad) => self.pads.push(pad),
+                    Err(e) => eprintln!("could not open game controller {}: {}", which, e),
+                }
+                Vec::new()
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.pads.retain(|p| p.instance_id() != which);
+                self.sticks.retain(|&(w, _, _)| w != which);
+                // it can't let go of anything any more.
+                ACTIONS
+                    .iter()
+                    .filter(|&&a| !self.buttons.keys_for(a).is_empty() || is_direction(a))
+                    .map(|&a| (a, false))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn stick(&mut self, which: i32, axis: Axis, value: i16) -> Vec<(Action, bool)> {
+        let (minus, plus) = match axis {
+            Axis::LeftX | Axis::RightX => (Action::Left, Action::Right),
+            Axis::LeftY | Axis::RightY => (Action::Up, Action::Down),
+            Axis::TriggerLeft | Axis::TriggerRight => return Vec::new(),
+        };
+        let direction = if value <= -STICK_THRESHOLD {
+            Some(minus)
+        } else if value >= STICK_THRESHOLD {
+            Some(plus)
+        } else {
+            None
+        };
+        let old = match self.sticks.iter_mut().find(|s| s.0 == which && s.1 == axis) {
+            Some(s) => ::std::mem::replace(&mut s.2, direction),
+            None => {
+                self.sticks.push((which, axis, direction));
+                None
+            }
+        };
+        let mut result = Vec::new();
+        if old != direction {
+            if let Some(a) = old {
+                result.push((a, false));
+            }
+            if let Some(a) = direction {
+                result.push((a, true));
+            }
+        }
+        result
+    }
+}
+
+fn load<'a, B: ::keys::Binding>(storage: &'a dyn Storage, key: &'static str) -> Bindings<'a, B> {
+    Bindings::load(storage, key).unwrap_or_else(|e| {
+        eprintln!("could not read bindings from {}: {}", key, e);
+        Bindings::defaults(storage, key)
+    })
+}
+
+// The preferences are kept as text, a line to each:
+//
+//     ghost = on
+fn load_prefs(storage: &dyn Storage, key: &str) -> Preferences {
+    let mut prefs = Preferences::default();
+    let bytes = match storage.read(key) {
+        Ok(bytes) => bytes.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("could not read settings from {}: {}", key, e);
+            return prefs;
+        }
+    };
+    for (n, line) in bytes.lines().map_while(Result::ok).enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let on = match parts.next().unwrap_or("").trim() {
+            "on" => true,
+            "off" => false,
+            v => {
+                eprintln!("{}:{}: expected on or off, not {}", key, n + 1, v);
+                continue;
+            }
+        };
+        match name {
+            "ghost" => prefs.ghost = on,
+            _ => eprintln!("{}:{}: unknown setting {}", key, n + 1, name),
+        }
+    }
+    prefs
+}
+
+fn pressed(action: Option<Action>, down: bool) -> Vec<(Action, bool)> {
+    action.into_iter().map(|a| (a, down)).collect()
+}
+
+fn is_direction(action: Action) -> bool {
+    matches!(action, Action::Left | Action::Right | Action::Up | Action::Down)
+}
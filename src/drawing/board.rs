@@ -1,9 +1,10 @@
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
-use sdl2::rect::Rect;
+use super::surface::{Rect, Surface};
 
 use imprint::Imprint;
 
+const PADDING_X: u32 = 1;
+const PADDING_Y: u32 = 1;
+
 pub struct BoardDrawingContext {
     pub offset_x: u32,
     pub offset_y: u32,
@@ -15,41 +16,66 @@ pub struct BoardDrawingContext {
 }
 
 impl BoardDrawingContext {
-    pub fn draw_box<T: RenderTarget>(&self, c: &mut Canvas<T>, px: i32, py: i32) -> Result<(), String> {
+    // the top left corner of a box on screen, if it is on the visible part
+    // of the board.
+    fn corner(&self, px: i32, py: i32) -> Option<(i32, i32)> {
         if px < 0 || px >= self.board_w as i32 {
-            return Ok(());
+            return None;
         };
-        if py < self.buffer_h as i32 || py >= self.board_h as i32 as i32 {
-            return Ok(());
+        if py < self.buffer_h as i32 || py >= self.board_h as i32 {
+            return None;
         };
-        const PADDING_X: u32 = 1;
-        const PADDING_Y: u32 = 1;
-        let w = self.box_w;
-        let h = self.box_h;
         let x = self.offset_x as i32 + px * self.box_w as i32;
         let y = self.offset_y as i32 + (py - self.buffer_h as i32) * self.box_h as i32;
+        Some((x, y))
+    }
+
+    pub fn draw_box<S: Surface + ?Sized>(&self, c: &mut S, px: i32, py: i32) -> Result<(), String> {
+        let (x, y) = match self.corner(px, py) {
+            Some(corner) => corner,
+            None => return Ok(()),
+        };
+        let w = self.box_w;
+        let h = self.box_h;
+        // boxes in a small window, or a small preview, may have no room
+        // for some of this.
         c.draw_rect(Rect::new(
             x + PADDING_X as i32,
             y + PADDING_Y as i32,
-            w - PADDING_X,
-            h - PADDING_Y,
+            w.saturating_sub(PADDING_X),
+            h.saturating_sub(PADDING_Y),
         ))?;
         c.draw_rect(Rect::new(
             x + PADDING_X as i32 + 1,
             y + PADDING_Y as i32 + 1,
-            w - PADDING_X - 2,
-            h - PADDING_Y - 2,
+            w.saturating_sub(PADDING_X + 2),
+            h.saturating_sub(PADDING_Y + 2),
         ))?;
         c.fill_rect(Rect::new(
             x + PADDING_X as i32 * 4,
             y + PADDING_Y as i32 * 4,
-            w - PADDING_X * 7,
-            h - PADDING_Y * 7,
+            w.saturating_sub(PADDING_X * 7),
+            h.saturating_sub(PADDING_Y * 7),
         ))
     }
-    pub fn fill_rect<T: RenderTarget>(
+
+    // just the outer edge of a box, for a piece that isn't really there.
+    pub fn draw_hollow_box<S: Surface + ?Sized>(&self, c: &mut S, px: i32, py: i32) -> Result<(), String> {
+        let (x, y) = match self.corner(px, py) {
+            Some(corner) => corner,
+            None => return Ok(()),
+        };
+        c.draw_rect(Rect::new(
+            x + PADDING_X as i32,
+            y + PADDING_Y as i32,
+            self.box_w.saturating_sub(PADDING_X),
+            self.box_h.saturating_sub(PADDING_Y),
+        ))
+    }
+
+    pub fn fill_rect<S: Surface + ?Sized>(
         &self,
-        c: &mut Canvas<T>,
+        c: &mut S,
         x1: i32,
         y1: i32,
         x2: i32,
@@ -62,22 +88,22 @@ impl BoardDrawingContext {
         }
         Ok(())
     }
-    pub fn fill_boxes<T: RenderTarget>(
+    pub fn fill_boxes<S: Surface + ?Sized>(
         &self,
-        c: &mut Canvas<T>,
+        c: &mut S,
         y1: i32,
         y2: i32,
     ) -> Result<(), String> {
         self.fill_rect(c,0,y1,self.board_w as i32, y2)
     }
 
-    pub fn fill_all_boxes<T: RenderTarget>(&self, c: &mut Canvas<T>) -> Result<(), String> {
+    pub fn fill_all_boxes<S: Surface + ?Sized>(&self, c: &mut S) -> Result<(), String> {
         self.fill_boxes(c, self.buffer_h as i32, self.board_h as i32)
     }
 
-    pub fn draw_imprint<A : Copy,T: RenderTarget>(
+    pub fn draw_imprint<A : Copy, S: Surface + ?Sized>(
         &self,
-        c: &mut Canvas<T>,
+        c: &mut S,
         p: &Imprint<A>,
         x: i32,
         y: i32,
@@ -92,4 +118,24 @@ impl BoardDrawingContext {
         }
         Ok(())
     }
+
+    // an imprint in hollow boxes, such as the ghost of where a piece would
+    // land.
+    pub fn draw_ghost<A : Copy, S: Surface + ?Sized>(
+        &self,
+        c: &mut S,
+        p: &Imprint<A>,
+        x: i32,
+        y: i32,
+    ) -> Result<(), String> {
+        let (w, h) = p.size();
+        for cy in 0..h {
+            for cx in 0..w {
+                if !p[(cx, cy)].is_empty() {
+                    self.draw_hollow_box(c, x + cx as i32, y + cy as i32)?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use super::surface::{Color, Rect, Surface};
+
+// An in-memory surface, for drawing without a display.
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    color: Color,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        let black = Color::rgb(0, 0, 0);
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .expect("framebuffer too big for memory");
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![black; len],
+            color: black,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    // rows from top to bottom, each from left to right.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    // saved as a PNG, unless the file name ends in .ppm
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        match path.extension() {
+            Some(e) if e == "ppm" => self.write_ppm(&mut file)?,
+            _ => self.write_png(&mut file)?,
+        }
+        file.flush()
+    }
+
+    pub fn write_ppm<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for p in &self.pixels {
+            w.write_all(&[p.r, p.g, p.b])?;
+        }
+        Ok(())
+    }
+
+    // An uncompressed PNG: the image data is wrapped in stored deflate
+    // blocks, which every PNG reader understands.
+    pub fn write_png<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty image"));
+        }
+        w.write_all(b"\x89PNG\r\n\x1a\n")?;
+        let mut header = Vec::new();
+        header.write_u32::<BigEndian>(self.width)?;
+        header.write_u32::<BigEndian>(self.height)?;
+        // 8 bit RGB, default compression and filtering, not interlaced.
+        header.write_all(&[8, 2, 0, 0, 0])?;
+        write_chunk(w, b"IHDR", &header)?;
+
+        let mut raw = Vec::new();
+        for row in self.pixels.chunks(self.width as usize) {
+            raw.push(0);
+            for p in row {
+                raw.extend_from_slice(&[p.r, p.g, p.b]);
+            }
+        }
+        let mut data = vec![0x78, 0x01];
+        let blocks = raw.chunks(0xFFFF).count();
+        for (i, block) in raw.chunks(0xFFFF).enumerate() {
+            data.push(if i + 1 == blocks { 1 } else { 0 });
+            data.extend_from_slice(&[block.len() as u8, (block.len() >> 8) as u8]);
+            data.extend_from_slice(&[!block.len() as u8, (!block.len() >> 8) as u8]);
+            data.extend_from_slice(block);
+        }
+        data.write_u32::<BigEndian>(adler32(&raw))?;
+        write_chunk(w, b"IDAT", &data)?;
+        write_chunk(w, b"IEND", &[])
+    }
+
+    fn plot(&mut self, x: i32, y: i32) {
+        if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+            let i = self.index(x as u32, y as u32);
+            self.pixels[i] = self.color;
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+impl Surface for Framebuffer {
+    fn set_draw_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn clear(&mut self) {
+        for p in &mut self.pixels {
+            *p = self.color;
+        }
+    }
+
+    fn draw_line(&mut self, (x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> Result<(), String> {
+        // Bresenham's algorithm
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            self.plot(x, y);
+            if x == x1 && y == y1 {
+                return Ok(());
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    fn draw_rect(&mut self, r: Rect) -> Result<(), String> {
+        if r.w == 0 || r.h == 0 {
+            return Ok(());
+        }
+        let (x1, y1) = (r.x + r.w as i32 - 1, r.y + r.h as i32 - 1);
+        self.draw_line((r.x, r.y), (x1, r.y))?;
+        self.draw_line((r.x, y1), (x1, y1))?;
+        self.draw_line((r.x, r.y), (r.x, y1))?;
+        self.draw_line((x1, r.y), (x1, y1))
+    }
+
+    fn fill_rect(&mut self, r: Rect) -> Result<(), String> {
+        for y in r.y..r.y + r.h as i32 {
+            for x in r.x..r.x + r.w as i32 {
+                self.plot(x, y);
+            }
+        }
+        Ok(())
+    }
+
+    fn present(&mut self) {}
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_u32::<BigEndian>(data.len() as u32)?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    w.write_u32::<BigEndian>(!crc32(crc32(0xFFFF_FFFF, kind), data))
+}
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &d in data {
+        a = (a + d as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -1,5 +1,4 @@
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
+use super::surface::Surface;
 
 pub struct LabelDrawingContext {
     pub w: i32,
@@ -10,9 +9,9 @@ pub struct LabelDrawingContext {
 }
 
 impl LabelDrawingContext {
-    pub fn draw<T: RenderTarget>(
+    pub fn draw<S: Surface + ?Sized>(
         &self,
-        c: &mut Canvas<T>,
+        c: &mut S,
         txt: &str,
         x0: i32,
         y0: i32,
@@ -71,10 +70,168 @@ impl LabelDrawingContext {
                     c.draw_line((x, y), (x, y + h))?;
                     c.draw_line((x, y + h), (x + w, y + h))?;
                 }
+                'R' => {
+                    c.draw_line((x, y), (x + w - 1, y))?;
+                    c.draw_line((x, y), (x, y + h))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h / 2 - 1))?;
+                    c.draw_line((x, y + h / 2), (x + w - 1, y + h / 2))?;
+                    c.draw_line((x + w / 2, y + h / 2), (x + w, y + h))?;
+                }
+                'U' => {
+                    c.draw_line((x, y), (x, y + h - 1))?;
+                    c.draw_line((x + 1, y + h), (x + w - 1, y + h))?;
+                    c.draw_line((x + w, y), (x + w, y + h - 1))?;
+                }
+                'M' => {
+                    c.draw_line((x, y + h), (x, y))?;
+                    c.draw_line((x, y), (x + w / 2, y + h / 2))?;
+                    c.draw_line((x + w / 2, y + h / 2), (x + w, y))?;
+                    c.draw_line((x + w, y), (x + w, y + h))?;
+                }
+                'A' => {
+                    c.draw_line((x, y + h), (x + w / 2, y))?;
+                    c.draw_line((x + w / 2, y), (x + w, y + h))?;
+                    c.draw_line((x + w / 4, y + h / 2), (x + w * 3 / 4, y + h / 2))?;
+                }
+                'B' => {
+                    c.draw_line((x, y), (x, y + h))?;
+                    c.draw_line((x, y), (x + w - 1, y))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h / 2 - 1))?;
+                    c.draw_line((x, y + h / 2), (x + w - 1, y + h / 2))?;
+                    c.draw_line((x + w, y + h / 2 + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x, y + h), (x + w - 1, y + h))?;
+                }
+                'C' => {
+                    c.draw_line((x + 1, y), (x + w, y))?;
+                    c.draw_line((x, y + 1), (x, y + h - 1))?;
+                    c.draw_line((x + 1, y + h), (x + w, y + h))?;
+                }
+                'D' => {
+                    c.draw_line((x, y), (x, y + h))?;
+                    c.draw_line((x, y), (x + w - 1, y))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x, y + h), (x + w - 1, y + h))?;
+                }
+                'F' => {
+                    c.draw_line((x, y), (x, y + h))?;
+                    c.draw_line((x, y), (x + w, y))?;
+                    c.draw_line((x, y + h / 2), (x + (w * 2 / 3), y + h / 2))?;
+                }
+                'G' => {
+                    c.draw_line((x + 1, y), (x + w, y))?;
+                    c.draw_line((x, y + 1), (x, y + h - 1))?;
+                    c.draw_line((x + 1, y + h), (x + w, y + h))?;
+                    c.draw_line((x + w, y + h / 2), (x + w, y + h - 1))?;
+                    c.draw_line((x + w / 2, y + h / 2), (x + w, y + h / 2))?;
+                }
+                'H' => {
+                    c.draw_line((x, y), (x, y + h))?;
+                    c.draw_line((x + w, y), (x + w, y + h))?;
+                    c.draw_line((x, y + h / 2), (x + w, y + h / 2))?;
+                }
+                'J' => {
+                    c.draw_line((x + w, y), (x + w, y + h - 1))?;
+                    c.draw_line((x + 1, y + h), (x + w - 1, y + h))?;
+                    c.draw_line((x, y + h / 2), (x, y + h - 1))?;
+                }
+                'K' => {
+                    c.draw_line((x, y), (x, y + h))?;
+                    c.draw_line((x, y + h / 2), (x + w, y))?;
+                    c.draw_line((x, y + h / 2), (x + w, y + h))?;
+                }
+                'Q' => {
+                    c.draw_line((x + 1, y), (x + w - 1, y))?;
+                    c.draw_line((x + 1, y + h), (x + w - 1, y + h))?;
+                    c.draw_line((x, y + 1), (x, y + h - 1))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x + w / 2, y + h / 2), (x + w, y + h))?;
+                }
+                'W' => {
+                    c.draw_line((x, y), (x + w / 4, y + h))?;
+                    c.draw_line((x + w / 4, y + h), (x + w / 2, y + h / 2))?;
+                    c.draw_line((x + w / 2, y + h / 2), (x + w * 3 / 4, y + h))?;
+                    c.draw_line((x + w * 3 / 4, y + h), (x + w, y))?;
+                }
+                'Y' => {
+                    c.draw_line((x, y), (x + w / 2, y + h / 2))?;
+                    c.draw_line((x + w, y), (x + w / 2, y + h / 2))?;
+                    c.draw_line((x + w / 2, y + h / 2), (x + w / 2, y + h))?;
+                }
+                'Z' => {
+                    c.draw_line((x, y), (x + w, y))?;
+                    c.draw_line((x + w, y), (x, y + h))?;
+                    c.draw_line((x, y + h), (x + w, y + h))?;
+                }
                 'V' => {
                     c.draw_line((x, y), (x + w / 2, y + h))?;
                     c.draw_line((x + w / 2, y + h), (x + w, y))?;
                 }
+                '0' => {
+                    c.draw_line((x + 1, y), (x + w - 1, y))?;
+                    c.draw_line((x + 1, y + h), (x + w - 1, y + h))?;
+                    c.draw_line((x, y + 1), (x, y + h - 1))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x + w, y + 1), (x, y + h - 1))?;
+                }
+                '1' => {
+                    c.draw_line((x + w / 4, y + h / 4), (x + w / 2, y))?;
+                    c.draw_line((x + w / 2, y), (x + w / 2, y + h))?;
+                    c.draw_line((x, y + h), (x + w, y + h))?;
+                }
+                '2' => {
+                    c.draw_line((x, y + 1), (x + 1, y))?;
+                    c.draw_line((x + 1, y), (x + w - 1, y))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h / 2 - 1))?;
+                    c.draw_line((x + w - 1, y + h / 2), (x, y + h))?;
+                    c.draw_line((x, y + h), (x + w, y + h))?;
+                }
+                '3' => {
+                    c.draw_line((x, y), (x + w - 1, y))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h / 2 - 1))?;
+                    c.draw_line((x + w / 3, y + h / 2), (x + w - 1, y + h / 2))?;
+                    c.draw_line((x + w, y + h / 2 + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x, y + h), (x + w - 1, y + h))?;
+                }
+                '4' => {
+                    c.draw_line((x + w * 3 / 4, y), (x, y + h * 2 / 3))?;
+                    c.draw_line((x, y + h * 2 / 3), (x + w, y + h * 2 / 3))?;
+                    c.draw_line((x + w * 3 / 4, y), (x + w * 3 / 4, y + h))?;
+                }
+                '5' => {
+                    c.draw_line((x, y), (x + w, y))?;
+                    c.draw_line((x, y), (x, y + h / 2))?;
+                    c.draw_line((x, y + h / 2), (x + w - 1, y + h / 2))?;
+                    c.draw_line((x + w, y + h / 2 + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x, y + h), (x + w - 1, y + h))?;
+                }
+                '6' => {
+                    c.draw_line((x + 1, y), (x + w, y))?;
+                    c.draw_line((x, y + 1), (x, y + h - 1))?;
+                    c.draw_line((x, y + h / 2), (x + w - 1, y + h / 2))?;
+                    c.draw_line((x + w, y + h / 2 + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x + 1, y + h), (x + w - 1, y + h))?;
+                }
+                '7' => {
+                    c.draw_line((x, y), (x + w, y))?;
+                    c.draw_line((x + w, y), (x + w / 3, y + h))?;
+                }
+                '8' => {
+                    c.draw_line((x + 1, y), (x + w - 1, y))?;
+                    c.draw_line((x, y + 1), (x, y + h - 1))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x + 1, y + h / 2), (x + w - 1, y + h / 2))?;
+                    c.draw_line((x + 1, y + h), (x + w - 1, y + h))?;
+                }
+                '9' => {
+                    c.draw_line((x + 1, y), (x + w - 1, y))?;
+                    c.draw_line((x, y + 1), (x, y + h / 2 - 1))?;
+                    c.draw_line((x + 1, y + h / 2), (x + w, y + h / 2))?;
+                    c.draw_line((x + w, y + 1), (x + w, y + h - 1))?;
+                    c.draw_line((x, y + h), (x + w - 1, y + h))?;
+                }
+                '-' => {
+                    c.draw_line((x + w / 4, y + h / 2), (x + w * 3 / 4, y + h / 2))?;
+                }
                 _ => {}
             }
             x += w + s;
@@ -1,6 +1,8 @@
 mod numeric;
 mod label;
 mod board;
+mod surface;
+mod framebuffer;
 
 pub mod tetris;
 pub mod snake;
@@ -10,13 +12,15 @@ use self::board::BoardDrawingContext;
 use self::label::LabelDrawingContext;
 use self::numeric::NumericDrawingContext;
 
-use game::{Game};
+use game::Game;
+use imprint::Imprint;
+use game::leaderboard::{Leaderboard, View};
+use game::score_table::TABLE_SIZE;
+use game::stats::{Kind, Stats};
+use TICK_RATE;
 
-
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+pub use self::surface::{Color, Rect, Surface};
+pub use self::framebuffer::Framebuffer;
 
 pub const RM_COLOR: Color = Color {
     r: 158,
@@ -47,19 +51,56 @@ pub const HI_COLOR: Color = Color {
 pub struct BaseDrawingContext {
     main: BoardDrawingContext,
     next: BoardDrawingContext,
+    // under the level, for games with a piece put aside.
+    hold: BoardDrawingContext,
     labels: LabelDrawingContext,
+    message: LabelDrawingContext,
     points: NumericDrawingContext,
     top: NumericDrawingContext,
     level: NumericDrawingContext,
     border: Rect,
-    label_positions: (i32, i32, i32),
+    label_positions: (i32, i32, i32, i32),
+}
+
+// How the player likes the games drawn. These are theirs rather than the
+// games', so they aren't saved with a game or recorded in its replays.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Preferences {
+    // whether to outline where a falling piece would land.
+    pub ghost: bool,
 }
 
 pub trait GameDrawingContext<G : Game> {
-    fn draw_game<T: RenderTarget>(&self, c: &mut Canvas<T>, g: &G) -> Result<(), String>;
+    fn draw_game<S: Surface + ?Sized>(&self, c: &mut S, g: &G, prefs: &Preferences) -> Result<(), String>;
     fn resize(&mut self, vp_w: u32, vp_h: u32);
 }
 
+// Draw a game into a new framebuffer, exactly as it would appear in a window
+// of the same size.
+pub fn screenshot<G: Game, C: GameDrawingContext<G>>(
+    ctx: &mut C,
+    g: &G,
+    width: u32,
+    height: u32,
+    prefs: &Preferences,
+) -> Result<Framebuffer, String> {
+    let mut fb = Framebuffer::new(width, height);
+    ctx.resize(width, height);
+    ctx.draw_game(&mut fb, g, prefs)?;
+    Ok(fb)
+}
+
+// A piece in a preview box, which shows the second and third rows of its
+// imprint. One with nothing in the second, as pieces turned the guideline
+// way have, is moved down into view.
+fn draw_preview<S: Surface + ?Sized, A: Copy>(
+    ctx: &BoardDrawingContext,
+    c: &mut S,
+    p: &Imprint<A>,
+) -> Result<(), String> {
+    let y = if p.first_row() == 0 { 1 } else { 0 };
+    ctx.draw_imprint(c, p, 0, y)
+}
 
 impl BaseDrawingContext {
     pub fn new(vp_w: u32, vp_h: u32, board_w: u32, board_h: u32, board_b: u32) -> Self {
@@ -71,19 +112,28 @@ impl BaseDrawingContext {
         let sidebar_x = vp_w - sidebar_w + box_w / 2 - PADDING_X;
         BaseDrawingContext {
             main: BoardDrawingContext {
-                offset_x: PADDING_X as u32 + 1,
-                offset_y: PADDING_Y as u32 + 1,
-                box_w: box_w,
-                box_h: box_h,
-                board_w: board_w,
-                board_h: board_h,
+                offset_x: PADDING_X + 1,
+                offset_y: PADDING_Y + 1,
+                box_w,
+                box_h,
+                board_w,
+                board_h,
                 buffer_h: board_b,
             },
             next: BoardDrawingContext {
                 offset_x: sidebar_x,
-                offset_y: PADDING_Y as u32 + 1 + box_h,
-                box_w: box_w,
-                box_h: box_h,
+                offset_y: PADDING_Y + 1 + box_h,
+                box_w,
+                box_h,
+                board_w: 4,
+                board_h: 3,
+                buffer_h: 1,
+            },
+            hold: BoardDrawingContext {
+                offset_x: sidebar_x,
+                offset_y: PADDING_Y + 1 + box_h * 14 + box_h / 2,
+                box_w,
+                box_h,
                 board_w: 4,
                 board_h: 3,
                 buffer_h: 1,
@@ -95,6 +145,13 @@ impl BaseDrawingContext {
                 offset_x: sidebar_x as i32,
                 offset_y: box_h as i32 / 4,
             },
+            message: LabelDrawingContext {
+                w: box_w as i32 * 2 / 5,
+                h: box_h as i32 * 2 / 3,
+                spacing: box_w as i32 / 4,
+                offset_x: PADDING_X as i32 + box_w as i32 / 2,
+                offset_y: box_h as i32 * 2,
+            },
             level: NumericDrawingContext {
                 x: sidebar_x as i32 + (box_w as i32 / 2 + box_w as i32 / 4) * 4,
                 y: box_h as i32 / 4 + box_h as i32 * 13,
@@ -122,16 +179,33 @@ impl BaseDrawingContext {
             border: Rect::new(
                 PADDING_X as i32,
                 PADDING_Y as i32,
-                box_w * board_w as u32 + PADDING_X + 1,
-                box_h * (board_h - board_b) as u32 + PADDING_Y + 1,
+                box_w * board_w + PADDING_X + 1,
+                box_h * (board_h - board_b) + PADDING_Y + 1,
+            ),
+            label_positions: (
+                box_h as i32 * 4,
+                box_h as i32 * 8,
+                box_h as i32 * 12,
+                box_h as i32 * 2,
             ),
-            label_positions: (box_h as i32 * 4, box_h as i32 * 8, box_h as i32 * 12),
         }
     }
 
-    pub fn draw<T: RenderTarget, G: Game>(
+    pub fn draw<S: Surface + ?Sized, G: Game>(
+        &self,
+        c: &mut S,
+        g: &G,
+    ) -> Result<(), String> {
+        self.draw_frame(c, g)?;
+        c.set_draw_color(if g.is_paused() { HI_COLOR } else { FG_COLOR });
+        self.main.draw_imprint(c, g.board(), 0, 0)?;
+        Ok(())
+    }
+
+    // everything but the board itself.
+    fn draw_frame<S: Surface + ?Sized, G: Game>(
         &self,
-        c: &mut Canvas<T>,
+        c: &mut S,
         g: &G,
     ) -> Result<(), String> {
         c.set_draw_color(RM_COLOR);
@@ -141,7 +215,14 @@ impl BaseDrawingContext {
         self.points.draw_bg(c)?;
         self.top.draw_bg(c)?;
         self.level.draw_bg(c)?;
-        self.next.fill_boxes(c, 1, 3)?;
+        let next = g.next();
+        if next.len() > 1 {
+            for i in 0..next.len() {
+                self.queue_slot(i).fill_boxes(c, 1, 3)?;
+            }
+        } else {
+            self.next.fill_boxes(c, 1, 3)?;
+        }
         c.set_draw_color(FG_COLOR);
         c.draw_rect(self.border)?;
         // self.labels.draw(c, "NEXT", 0, 2)?;
@@ -151,13 +232,203 @@ impl BaseDrawingContext {
         self.points.draw_num(c, g.score())?;
         self.top.draw_num(c, g.top_score())?;
         self.level.draw_num(c, g.current_level() + 1)?;
-        match g.next() {
-            Some(n) => self.next.draw_imprint(c, n, 0, 0)?,
-            None => {}
+        match next.len() {
+            0 => {}
+            1 => draw_preview(&self.next, c, next[0])?,
+            _ => {
+                for (i, p) in next.iter().enumerate() {
+                    draw_preview(&self.queue_slot(i), c, p)?;
+                }
+            }
+        }
+        if g.can_resume() {
+            c.set_draw_color(HI_COLOR);
+            self.labels.draw(c, "RESUME", 0, self.label_positions.3)?;
         }
-        c.set_draw_color(if g.is_paused() { HI_COLOR } else { FG_COLOR });
-        self.main.draw_imprint(c, &g.board(), 0, 0)?;
         Ok(())
     }
-}
 
+    // where the i-th of several upcoming pieces goes: at half size, stacked
+    // three to a column from the top of the sidebar down to the points,
+    // with a little space between each.
+    fn queue_slot(&self, i: usize) -> BoardDrawingContext {
+        let (box_w, box_h) = (self.next.box_w, self.next.box_h);
+        BoardDrawingContext {
+            offset_x: self.next.offset_x + (i / 3) as u32 * (box_w * 9 / 4),
+            offset_y: self.next.offset_y - box_h + (i % 3) as u32 * (box_h * 4 / 3),
+            box_w: box_w / 2,
+            box_h: box_h / 2,
+            board_w: 4,
+            board_h: 3,
+            buffer_h: 1,
+        }
+    }
+
+    // the hold box, with the piece put aside in it if there is one.
+    pub fn draw_hold<S: Surface + ?Sized, A: Copy>(
+        &self,
+        c: &mut S,
+        held: Option<&Imprint<A>>,
+    ) -> Result<(), String> {
+        c.set_draw_color(BG_COLOR);
+        self.hold.fill_boxes(c, 1, 3)?;
+        if let Some(p) = held {
+            c.set_draw_color(FG_COLOR);
+            draw_preview(&self.hold, c, p)?;
+        }
+        Ok(())
+    }
+
+    // a line of text across the board, counting rows in boxes from the top.
+    fn draw_row<S: Surface + ?Sized>(&self, c: &mut S, txt: &str, row: i32) -> Result<(), String> {
+        let box_h = self.main.box_h as i32;
+        self.message.draw(c, txt, 0, (row - 2) * box_h + box_h / 6)
+    }
+
+    // Draw the score table or initials entry in place of the board.
+    pub fn draw_leaderboard<S: Surface + ?Sized, G: Game>(
+        &self,
+        c: &mut S,
+        g: &G,
+        board: &Leaderboard<'_>,
+    ) -> Result<(), String> {
+        self.draw_frame(c, g)?;
+        match board.view {
+            View::Closed => {}
+            View::Browsing { level, variant, place } => {
+                let scores = board.scores(level, variant);
+                c.set_draw_color(FG_COLOR);
+                self.draw_row(c, "TOP SCORES", 1)?;
+                for i in 0..TABLE_SIZE {
+                    c.set_draw_color(if i == place { HI_COLOR } else { FG_COLOR });
+                    let line = match scores.get(i) {
+                        Some(s) => format!(
+                            "{:>2} {} {:>6}",
+                            i + 1,
+                            String::from_utf8_lossy(&s.initials),
+                            s.points
+                        ),
+                        None => format!("{:>2} ---", i + 1),
+                    };
+                    self.draw_row(c, &line, i as i32 + 3)?;
+                }
+                if let Some(s) = scores.get(place) {
+                    c.set_draw_color(FG_COLOR);
+                    self.draw_row(c, &format!("LEVEL {}", s.level + 1), 14)?;
+                    self.draw_row(c, &format!("{} {}", board.detail, s.detail), 15)?;
+                    if s.date != 0 {
+                        let (y, m, d) = s.day();
+                        self.draw_row(c, &format!("{}-{:02}-{:02}", y, m, d), 16)?;
+                    }
+                    if let Some(mode) = board.modes.get(s.mode as usize) {
+                        self.draw_row(c, mode, 17)?;
+                    }
+                }
+            }
+            View::Naming { score, letter, .. } => {
+                c.set_draw_color(FG_COLOR);
+                self.draw_row(c, "NEW HIGH", 2)?;
+                self.draw_row(c, "SCORE", 3)?;
+                self.draw_row(c, &score.points.to_string(), 5)?;
+                self.draw_row(c, "NAME", 8)?;
+                let advance = self.message.w + self.message.spacing;
+                for (i, &ch) in score.initials.iter().enumerate() {
+                    c.set_draw_color(if i == letter { HI_COLOR } else { FG_COLOR });
+                    let box_h = self.main.box_h as i32;
+                    let txt = (ch as char).to_string();
+                    self.message.draw(c, &txt, (i as i32 + 3) * advance, 8 * box_h + box_h / 6)?;
+                }
+            }
+        }
+        self.draw_warning(c, g)?;
+        c.present();
+        Ok(())
+    }
+
+    // Whatever has to be shown over a game in play: any warning, and any
+    // achievement just earned.
+    pub fn draw_notices<S: Surface + ?Sized, G: Game>(&self, c: &mut S, g: &G) -> Result<(), String> {
+        self.draw_warning(c, g)?;
+        match g.achievement() {
+            Some(name) => self.draw_popup(c, name),
+            None => Ok(()),
+        }
+    }
+
+    // A warning across the top row of the board, which is cleared for it.
+    fn draw_warning<S: Surface + ?Sized, G: Game>(&self, c: &mut S, g: &G) -> Result<(), String> {
+        if let Some(warning) = g.warning() {
+            let top = self.main.buffer_h as i32;
+            c.set_draw_color(BG_COLOR);
+            self.main.fill_boxes(c, top, top + 1)?;
+            c.set_draw_color(HI_COLOR);
+            self.draw_row(c, warning, 0)?;
+        }
+        Ok(())
+    }
+
+    // Announce an achievement over the middle of the board.
+    fn draw_popup<S: Surface + ?Sized>(&self, c: &mut S, name: &str) -> Result<(), String> {
+        let top = self.main.buffer_h as i32 + 8;
+        c.set_draw_color(BG_COLOR);
+        self.main.fill_boxes(c, top, top + 4)?;
+        c.set_draw_color(HI_COLOR);
+        let (box_w, box_h) = (self.main.box_w, self.main.box_h);
+        c.draw_rect(Rect::new(
+            self.main.offset_x as i32,
+            (self.main.offset_y + box_h * 8) as i32,
+            box_w * self.main.board_w + 1,
+            box_h * 4 + 1,
+        ))?;
+        self.draw_row(c, "ACHIEVED", 9)?;
+        self.draw_row(c, name, 10)
+    }
+
+    // Draw the lifetime statistics in place of the board, with their values
+    // lined up on the right.
+    pub fn draw_stats<S: Surface + ?Sized, G: Game>(
+        &self,
+        c: &mut S,
+        g: &G,
+        stats: &Stats<'_>,
+    ) -> Result<(), String> {
+        // as many letters as fit across the board.
+        const WIDTH: usize = 14;
+        self.draw_frame(c, g)?;
+        c.set_draw_color(FG_COLOR);
+        self.draw_row(c, "STATISTICS", 1)?;
+        for (i, &(name, kind, value)) in stats.entries().iter().enumerate() {
+            let value = match kind {
+                Kind::Time => {
+                    let minutes = value / (TICK_RATE as u64 * 60);
+                    format!("{}H {:02}M", minutes / 60, minutes % 60)
+                }
+                Kind::Count | Kind::Best => value.to_string(),
+            };
+            let line = format!("{} {:>w$}", name, value, w = WIDTH.saturating_sub(name.len() + 1));
+            self.draw_row(c, &line, i as i32 + 3)?;
+        }
+        c.present();
+        Ok(())
+    }
+
+    // Draw a screen of text in place of a game, such as the key setup.
+    pub fn draw_message<S: Surface + ?Sized>(
+        &self,
+        c: &mut S,
+        lines: &[&str],
+    ) -> Result<(), String> {
+        c.set_draw_color(RM_COLOR);
+        c.clear();
+        c.set_draw_color(BG_COLOR);
+        self.main.fill_all_boxes(c)?;
+        c.set_draw_color(FG_COLOR);
+        c.draw_rect(self.border)?;
+        c.set_draw_color(HI_COLOR);
+        for (i, line) in lines.iter().enumerate() {
+            self.message.draw(c, line, 0, i as i32 * self.main.box_h as i32 * 2)?;
+        }
+        c.present();
+        Ok(())
+    }
+}
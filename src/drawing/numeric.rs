@@ -1,5 +1,4 @@
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
+use super::surface::Surface;
 
 
 pub struct NumericDrawingContext {
@@ -12,17 +11,17 @@ pub struct NumericDrawingContext {
 }
 
 impl NumericDrawingContext {
-    pub fn draw_bg<T: RenderTarget>(&self, c: &mut Canvas<T>) -> Result<(), String> {
+    pub fn draw_bg<S: Surface + ?Sized>(&self, c: &mut S) -> Result<(), String> {
         self.draw(c, DigitsBG::new(self.max_digits, 8))
     }
 
-    pub fn draw_num<T: RenderTarget>(&self, c: &mut Canvas<T>, num: u32) -> Result<(), String> {
+    pub fn draw_num<S: Surface + ?Sized>(&self, c: &mut S, num: u32) -> Result<(), String> {
         let max = 10u32.pow(self.max_digits) - 1;
         self.draw(c, Digits::new(if num <= max { num } else { max }))
     }
 
-    fn draw_horiz_segment<T: RenderTarget>(
-        c: &mut Canvas<T>,
+    fn draw_horiz_segment<S: Surface + ?Sized>(
+        c: &mut S,
         x1: i32,
         x2: i32,
         y: i32,
@@ -36,8 +35,8 @@ impl NumericDrawingContext {
         Ok(())
     }
 
-    fn draw_vert_segment<T: RenderTarget>(
-        c: &mut Canvas<T>,
+    fn draw_vert_segment<S: Surface + ?Sized>(
+        c: &mut S,
         x: i32,
         y1: i32,
         y2: i32,
@@ -51,7 +50,7 @@ impl NumericDrawingContext {
         Ok(())
     }
 
-    fn draw<I, T: RenderTarget>(&self, c: &mut Canvas<T>, num: I) -> Result<(), String>
+    fn draw<I, S: Surface + ?Sized>(&self, c: &mut S, num: I) -> Result<(), String>
     where
         I: Iterator<Item = u32>,
     {
@@ -147,7 +146,7 @@ struct Digits {
 
 impl Digits {
     fn new(n: u32) -> Self {
-        Digits { n: n, i: n == 0 }
+        Digits { n, i: n == 0 }
     }
 }
 
@@ -178,7 +177,7 @@ struct DigitsBG {
 
 impl DigitsBG {
     fn new(n: u32, y: u32) -> Self {
-        DigitsBG { n: n, y: y }
+        DigitsBG { n, y }
     }
 }
 
@@ -1,7 +1,5 @@
 use game::robots::{Robots, Status};
 use game::robots;
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
 
 use super::*;
 
@@ -33,7 +31,13 @@ impl<'a> GameDrawingContext<Robots<'a>> for DrawingContext {
             BaseDrawingContext::new(vp_w, vp_h, robots::WIDTH as u32, robots::HEIGHT as u32, 0)
     }
 
-    fn draw_game<T: RenderTarget>(&self, c: &mut Canvas<T>, g: &Robots) -> Result<(), String> {
+    fn draw_game<S: Surface + ?Sized>(&self, c: &mut S, g: &Robots, _: &Preferences) -> Result<(), String> {
+        if let Some(board) = g.leaderboard() {
+            return self.ctx.draw_leaderboard(c, g, board);
+        }
+        if let Some(stats) = g.stats() {
+            return self.ctx.draw_stats(c, g, stats);
+        }
         self.ctx.draw(c, g)?;
         let main = &self.ctx.main;
         match g.status {
@@ -164,6 +168,7 @@ impl<'a> GameDrawingContext<Robots<'a>> for DrawingContext {
                 }
             }
         }
+        self.ctx.draw_notices(c, g)?;
         c.present();
         Ok(())
     }
@@ -1,7 +1,5 @@
 use game::snake::{Snake, Status};
 use game::snake;
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
 
 use super::*;
 
@@ -26,7 +24,13 @@ impl<'a> GameDrawingContext<Snake<'a>> for DrawingContext {
         self.ctx = BaseDrawingContext::new(vp_w, vp_h, snake::WIDTH as u32, snake::HEIGHT as u32, 0)
     }
 
-    fn draw_game<T: RenderTarget>(&self, c: &mut Canvas<T>, g: &Snake) -> Result<(), String> {
+    fn draw_game<S: Surface + ?Sized>(&self, c: &mut S, g: &Snake, _: &Preferences) -> Result<(), String> {
+        if let Some(board) = g.leaderboard() {
+            return self.ctx.draw_leaderboard(c, g, board);
+        }
+        if let Some(stats) = g.stats() {
+            return self.ctx.draw_stats(c, g, stats);
+        }
         self.ctx.draw(c, g)?;
         let main = &self.ctx.main;
         match g.status {
@@ -154,6 +158,7 @@ impl<'a> GameDrawingContext<Snake<'a>> for DrawingContext {
                 }
             }
         }
+        self.ctx.draw_notices(c, g)?;
         c.present();
         Ok(())
     }
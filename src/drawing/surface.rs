@@ -0,0 +1,39 @@
+// The handful of drawing operations the games need. Coordinates are in
+// pixels, and lines include both of their end points.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, w: u32, h: u32) -> Rect {
+        Rect { x, y, w, h }
+    }
+}
+
+pub trait Surface {
+    fn set_draw_color(&mut self, color: Color);
+    fn clear(&mut self);
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32)) -> Result<(), String>;
+    fn draw_rect(&mut self, r: Rect) -> Result<(), String>;
+    fn fill_rect(&mut self, r: Rect) -> Result<(), String>;
+    fn present(&mut self);
+}
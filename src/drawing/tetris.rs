@@ -1,7 +1,5 @@
 use game::tetris::{Tetris, Status};
 use game::tetris;
-use sdl2::render::RenderTarget;
-use sdl2::render::Canvas;
 
 use super::*;
 
@@ -34,14 +32,27 @@ impl<'a> GameDrawingContext<Tetris<'a>> for DrawingContext {
             tetris::BUFFER as u32,
         )
     }
-    fn draw_game<T: RenderTarget>(&self, c: &mut Canvas<T>, g: &Tetris) -> Result<(), String> {
+    fn draw_game<S: Surface + ?Sized>(&self, c: &mut S, g: &Tetris, prefs: &Preferences) -> Result<(), String> {
+        if let Some(board) = g.leaderboard() {
+            return self.ctx.draw_leaderboard(c, g, board);
+        }
+        if let Some(stats) = g.stats() {
+            return self.ctx.draw_stats(c, g, stats);
+        }
         self.ctx.draw(c, g)?;
+        if g.config.hold {
+            self.ctx.draw_hold(c, g.held.as_ref().map(|p| p.imprint()))?;
+        }
         let main = &self.ctx.main;
         match g.status {
             Status::Active | Status::Paused => {
+                if prefs.ghost {
+                    let (x, y) = g.landing();
+                    main.draw_ghost(c, g.current.imprint(), x, y)?;
+                }
                 main.draw_imprint(
                     c,
-                    &g.current.imprint(),
+                    g.current.imprint(),
                     g.position.0,
                     g.position.1,
                 )?;
@@ -50,7 +61,7 @@ impl<'a> GameDrawingContext<Tetris<'a>> for DrawingContext {
                 c.set_draw_color(HI_COLOR);
                 main.draw_imprint(
                     c,
-                    &g.current.imprint(),
+                    g.current.imprint(),
                     g.position.0,
                     g.position.1,
                 )?;
@@ -124,6 +135,8 @@ impl<'a> GameDrawingContext<Tetris<'a>> for DrawingContext {
                 for &(x, y) in points.iter() {
                     main.draw_box(c, x - o, y)?;
                 }
+                c.set_draw_color(FG_COLOR);
+                self.ctx.draw_row(c, g.config.randomizer.label(), 12)?;
             }
 
             Status::Clearing(f) => {
@@ -133,11 +146,12 @@ impl<'a> GameDrawingContext<Tetris<'a>> for DrawingContext {
                 }
             }
 
-            Status::Placing(p, x, y) => {
+            Status::Placing(p, x, y, _) => {
                 c.set_draw_color(HI_COLOR);
-                main.draw_imprint(c, &p.imprint(), x, y)?;
+                main.draw_imprint(c, p.imprint(), x, y)?;
             }
         }
+        self.ctx.draw_notices(c, g)?;
         c.present();
         Ok(())
     }
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use persist;
+use storage::{self, Storage};
+use TICK_RATE;
+
+const MAGIC: &[u8; 4] = b"BGAC";
+const VERSION: u32 = 1;
+// how long each newly earned achievement is shown for.
+const POPUP_TICKS: u32 = TICK_RATE * 3;
+
+// Something worth doing in a game, checked against its state every tick.
+pub struct Achievement<G> {
+    // shown when it is earned, and the name it is stored under.
+    pub name: &'static str,
+    pub earned: fn(&G) -> bool,
+}
+
+// The achievements earned so far in a game, kept from one run to the next,
+// and the popups announcing any just earned.
+pub struct Achievements<'a> {
+    earned: Vec<String>,
+    storage: &'a dyn Storage,
+    key: String,
+    // waiting to be shown, the one showing first.
+    popups: VecDeque<&'static str>,
+    // how much longer the one showing stays up.
+    timer: u32,
+}
+
+impl<'a> Achievements<'a> {
+    pub fn new(storage: &'a dyn Storage, key: &str) -> Achievements<'a> {
+        let key = format!("{}.achievements", key);
+        let earned = storage::read_with_backup(storage, &key, |bytes| read_earned(&mut &bytes[..]))
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {}", key, e);
+                None
+            });
+        Achievements {
+            earned: earned.unwrap_or_default(),
+            storage,
+            key,
+            popups: VecDeque::new(),
+            timer: 0,
+        }
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.earned.iter().any(|e| e == name)
+    }
+
+    // the achievements in `list` the game has just earned, checked at the
+    // end of each tick and handed to `earn`.
+    pub fn newly_earned<G>(&self, game: &G, list: &[Achievement<G>]) -> Vec<&'static str> {
+        list.iter()
+            .filter(|a| !self.has(a.name) && (a.earned)(game))
+            .map(|a| a.name)
+            .collect()
+    }
+
+    // keeps and announces each of `names`.
+    pub fn earn(&mut self, names: &[&'static str]) {
+        if names.is_empty() {
+            return;
+        }
+        for &name in names {
+            self.earned.push(name.to_string());
+            self.popups.push_back(name);
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let mut w = Vec::new();
+        let written = write_earned(&mut w, &self.earned)
+            .and_then(|_| storage::write_with_backup(self.storage, &self.key, &w));
+        if let Err(e) = written {
+            eprintln!("{}: {}", self.key, e);
+        }
+    }
+
+    // counts down the popup showing, moving on to the next once it is done.
+    pub fn tick(&mut self) {
+        if self.popups.is_empty() {
+            return;
+        }
+        self.timer += 1;
+        if self.timer >= POPUP_TICKS {
+            self.timer = 0;
+            self.popups.pop_front();
+        }
+    }
+
+    // the achievement being announced, if any.
+    pub fn popup(&self) -> Option<&'static str> {
+        self.popups.front().cloned()
+    }
+}
+
+fn write_earned<W: Write>(w: &mut W, earned: &[String]) -> io::Result<()> {
+    persist::write_header(w, MAGIC, VERSION)?;
+    w.write_u32::<LittleEndian>(earned.len() as u32)?;
+    for name in earned {
+        persist::write_string(w, name)?;
+    }
+    Ok(())
+}
+
+fn read_earned<R: Read>(r: &mut R) -> io::Result<Vec<String>> {
+    if persist::read_header(r, MAGIC)? != VERSION {
+        return Err(persist::invalid_data("unsupported achievements version"));
+    }
+    let n = persist::read_len(r)?;
+    let mut earned = Vec::new();
+    for _ in 0..n {
+        earned.push(persist::read_string(r)?);
+    }
+    Ok(earned)
+}
@@ -0,0 +1,156 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use game::InputState;
+use game::score_table::{Score, ScoreTable, TABLE_SIZE};
+use storage::Storage;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum View {
+    Closed,
+    // looking through the table for a level and variant, with one of its
+    // places picked out.
+    Browsing { level: u32, variant: u32, place: usize },
+    // a game has just made the table, and wants initials to go with it.
+    Naming { level: u32, variant: u32, score: Score, letter: usize },
+}
+
+// A game's score tables, along with the screens that show them and take
+// down the initials of anyone who makes it in. While one of those is up, the
+// game hands its input over to the leaderboard.
+pub struct Leaderboard<'a> {
+    table: ScoreTable<'a>,
+    // what the detail of each score counts, such as "LINES".
+    pub detail: &'static str,
+    // the names of the game's modes, by number, for games with more than one.
+    pub modes: &'static [&'static str],
+    pub view: View,
+    initials: [u8; 3],
+    // set when the table couldn't be read or written, for the game to show.
+    warning: Option<&'static str>,
+}
+
+impl<'a> Leaderboard<'a> {
+    pub fn new(
+        storage: &'a dyn Storage,
+        key: &'a str,
+        shape: (usize, usize),
+        legacy: (usize, usize),
+        detail: &'static str,
+        modes: &'static [&'static str],
+    ) -> Leaderboard<'a> {
+        let mut warning = None;
+        let table = ScoreTable::new(storage, key, shape, legacy).unwrap_or_else(|e| {
+            eprintln!("{}: {}", key, e);
+            warning = Some("SCORES NOT LOADED");
+            ScoreTable::empty(storage, key, shape)
+        });
+        Leaderboard {
+            table,
+            detail,
+            modes,
+            view: View::Closed,
+            initials: *b"AAA",
+            warning,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.view != View::Closed
+    }
+
+    pub fn warning(&self) -> Option<&'static str> {
+        self.warning.or_else(|| self.table.warning())
+    }
+
+    pub fn get_top_score(&self, level: u32, variant: u32) -> u32 {
+        self.table.get_top_score(level, variant)
+    }
+
+    pub fn scores(&self, level: u32, variant: u32) -> &[Score] {
+        self.table.scores(level, variant)
+    }
+
+    pub fn browse(&mut self, level: u32, variant: u32) {
+        self.view = View::Browsing { level, variant, place: 0 };
+    }
+
+    // called at the end of every game; asks for initials if it made the
+    // table, returning whether it did. Buttons still held from the game are
+    // let go of, so that they don't go straight to the initials.
+    pub fn finish(&mut self, input: &mut InputState, level: u32, variant: u32, score: Score) -> bool {
+        if !self.table.qualifies(level, variant, score.points) {
+            return false;
+        }
+        *input = InputState::new();
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let score = Score {
+            initials: self.initials,
+            date,
+            ..score
+        };
+        self.view = View::Naming { level, variant, score, letter: 0 };
+        true
+    }
+
+    pub fn tick(&mut self, input: &mut InputState) {
+        match self.view {
+            View::Closed => {}
+            View::Browsing { ref mut place, .. } => {
+                if input.up {
+                    input.up = false;
+                    *place = place.saturating_sub(1);
+                }
+                if input.down {
+                    input.down = false;
+                    *place = (*place + 1).min(TABLE_SIZE - 1);
+                }
+                if input.escape || input.drop || input.button_a || input.button_b {
+                    input.escape = false;
+                    input.drop = false;
+                    input.button_a = false;
+                    input.button_b = false;
+                    self.view = View::Closed;
+                }
+            }
+            View::Naming { level, variant, ref mut score, ref mut letter } => {
+                let c = &mut score.initials[*letter];
+                if input.up {
+                    input.up = false;
+                    *c = if *c >= b'Z' { b'A' } else { *c + 1 };
+                }
+                if input.down {
+                    input.down = false;
+                    *c = if *c <= b'A' { b'Z' } else { *c - 1 };
+                }
+                if input.left {
+                    input.left = false;
+                    *letter = letter.saturating_sub(1);
+                }
+                if input.right {
+                    input.right = false;
+                    *letter = (*letter + 1).min(2);
+                }
+                if input.drop || input.button_a || input.escape {
+                    input.drop = false;
+                    input.button_a = false;
+                    input.escape = false;
+                    let score = *score;
+                    self.initials = score.initials;
+                    // the score stays in the table for now even if it
+                    // can't be written out.
+                    let place = self.table.update_scores(level, variant, score);
+                    self.warning = match self.table.save_scores() {
+                        Ok(()) => None,
+                        Err(e) => {
+                            eprintln!("{}: {}", self.table.key(), e);
+                            Some("SCORES NOT SAVED")
+                        }
+                    };
+                    self.view = View::Browsing { level, variant, place: place.unwrap_or(0) };
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,25 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
 use imprint::Imprint;
+use self::achievements::Achievements;
+use self::leaderboard::Leaderboard;
+use self::stats::Stats;
+use persist::Persist;
 
+pub mod achievements;
 pub mod robots;
 pub mod tetris;
 pub mod snake;
+pub mod leaderboard;
+pub mod repeat;
+pub mod saved;
+pub mod score_table;
+pub mod speed;
+pub mod stats;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct InputState {
     pub escape: bool,
     pub down: bool,
@@ -15,12 +31,25 @@ pub struct InputState {
     pub drop: bool,
     pub next: bool,
     pub prev: bool,
-    pub skip: u32,
+    pub stats: bool,
+    pub hold: bool,
+    pub mode: bool,
+    // how far the held directions move this tick, left and up counting as
+    // less than nothing, as the auto-repeat works it out.
+    pub shift: i8,
+    pub climb: i8,
+    // rows down is to drop this tick, at the soft drop rate.
+    pub fall: u8,
 }
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InputState {
     pub fn new() -> InputState {
         InputState {
-            skip: 0,
             escape: false,
             down: false,
             left: false,
@@ -30,9 +59,179 @@ impl InputState {
             up: false,
             drop: false,
             next: false,
-            prev: false
+            prev: false,
+            stats: false,
+            hold: false,
+            mode: false,
+            shift: 0,
+            climb: 0,
+            fall: 0,
+        }
+    }
+
+    pub fn set(&mut self, action: Action, pressed: bool) {
+        match action {
+            Action::Escape => self.escape = pressed,
+            Action::Left => self.left = pressed,
+            Action::Right => self.right = pressed,
+            Action::Up => self.up = pressed,
+            Action::Down => self.down = pressed,
+            Action::ButtonA => self.button_a = pressed,
+            Action::ButtonB => self.button_b = pressed,
+            Action::Drop => self.drop = pressed,
+            Action::Next => self.next = pressed,
+            Action::Prev => self.prev = pressed,
+            Action::Stats => self.stats = pressed,
+            Action::Hold => self.hold = pressed,
+            Action::Mode => self.mode = pressed,
+            // a setting of the player's, which the front end sees to.
+            Action::Ghost => {}
+        }
+    }
+}
+
+// The buttons of the console. Front ends map their own keys or buttons to
+// these.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Escape,
+    Left,
+    Right,
+    Up,
+    Down,
+    ButtonA,
+    ButtonB,
+    Drop,
+    Next,
+    Prev,
+    Stats,
+    Hold,
+    // picks the next of a game's modes on its menu.
+    Mode,
+    // shows or hides where the piece would land.
+    Ghost,
+}
+
+pub static ACTIONS: [Action; 14] = [
+    Action::Escape,
+    Action::Left,
+    Action::Right,
+    Action::Up,
+    Action::Down,
+    Action::ButtonA,
+    Action::ButtonB,
+    Action::Drop,
+    Action::Next,
+    Action::Prev,
+    Action::Stats,
+    Action::Hold,
+    Action::Mode,
+    Action::Ghost,
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Action::Escape => "escape",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::ButtonA => "button_a",
+            Action::ButtonB => "button_b",
+            Action::Drop => "drop",
+            Action::Next => "next",
+            Action::Prev => "prev",
+            Action::Stats => "stats",
+            Action::Hold => "hold",
+            Action::Mode => "mode",
+            Action::Ghost => "ghost",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().cloned().find(|a| a.name() == name)
+    }
+}
+
+impl Persist for InputState {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let buttons = [
+            self.escape, self.down, self.up, self.left, self.right,
+            self.button_a, self.button_b, self.drop, self.next, self.prev,
+            self.stats, self.hold, self.mode,
+        ];
+        let mut bits = 0u16;
+        for (i, &b) in buttons.iter().enumerate() {
+            if b {
+                bits |= 1 << i;
+            }
         }
+        w.write_u16::<LittleEndian>(bits)?;
+        w.write_i8(self.shift)?;
+        w.write_u8(self.fall)?;
+        w.write_i8(self.climb)
     }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<InputState> {
+        let mut input = read_v3_input(r)?;
+        input.climb = r.read_i8()?;
+        Ok(input)
+    }
+}
+
+// an input state as replays from before up and down repeated stored it.
+// Games moved up or down once a press, and cleared the button as they did,
+// so what was left of it is the move.
+pub fn read_v3_input<R: Read>(r: &mut R) -> io::Result<InputState> {
+    let mut input = read_buttons(r)?;
+    input.shift = r.read_i8()?;
+    input.fall = r.read_u8()?;
+    input.climb = input.down as i8 - input.up as i8;
+    Ok(input)
+}
+
+// an input state as replays from before the auto-repeat stored it, with a
+// count of the ticks left or right had been held for, up to three. Tetris
+// moved on the first of them and from the fourth on, and dropped a row every
+// tick down was held.
+pub fn read_v2_input<R: Read>(r: &mut R) -> io::Result<InputState> {
+    let mut input = read_buttons(r)?;
+    let skip = r.read_u32::<LittleEndian>()?;
+    let moves = (skip == 0 || skip > 2) as i8;
+    input.shift = if input.left {
+        -moves
+    } else if input.right {
+        moves
+    } else {
+        0
+    };
+    input.fall = input.down as u8;
+    input.climb = input.down as i8 - input.up as i8;
+    Ok(input)
+}
+
+fn read_buttons<R: Read>(r: &mut R) -> io::Result<InputState> {
+    let bits = r.read_u16::<LittleEndian>()?;
+    let bit = |i: u16| bits & (1 << i) != 0;
+    Ok(InputState {
+        escape: bit(0),
+        down: bit(1),
+        up: bit(2),
+        left: bit(3),
+        right: bit(4),
+        button_a: bit(5),
+        button_b: bit(6),
+        drop: bit(7),
+        next: bit(8),
+        prev: bit(9),
+        stats: bit(10),
+        hold: bit(11),
+        mode: bit(12),
+        shift: 0,
+        climb: 0,
+        fall: 0,
+    })
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -43,14 +242,89 @@ pub enum TickResult {
     PrevGame
 }
 
+// Something that happened during a tick, for anything outside the game that
+// wants to follow along.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Event {
+    PieceLocked,
+    // how many lines went at once.
+    LinesCleared(u32),
+    // the level just reached.
+    LevelUp(u32),
+    FoodEaten,
+    BonusEaten,
+    // how many robots were scrapped at once.
+    RobotsScrapped(u32),
+    Teleport,
+    GameOver,
+    // the points that made the score table.
+    HighScore(u32),
+}
+
+// The settings a game's menu offers, chosen by name instead, as on the
+// command line. Values are as the menu shows them, so levels count from 1.
+pub trait Options {
+    // false if the game has no setting by that name.
+    fn set_option(&mut self, name: &str, value: u32) -> Result<bool, String>;
+}
+
+// Set each of the options given on the command line that the game has,
+// passing over the rest.
+pub fn apply_options<C: Options>(config: &mut C, options: &[(&str, u32)]) -> Result<(), String> {
+    for &(name, value) in options {
+        config.set_option(name, value)?;
+    }
+    Ok(())
+}
+
+pub fn check_range(name: &str, value: u32, min: u32, max: u32) -> Result<u32, String> {
+    if value < min || value > max {
+        return Err(format!("{} must be from {} to {}", name, min, max));
+    }
+    Ok(value)
+}
+
 pub trait Game {
     type CellData : Copy;
+    type Config : Persist + Clone + Default + Options;
     fn current_level(&self) -> u32;
     fn score(&self) -> u32;
     fn top_score(&self) -> u32;
     fn board(&self) -> &Imprint<Self::CellData>;
-    fn next(&self) -> Option<&Imprint<Self::CellData>>;
+    // what is coming up, soonest first, to be shown beside the board.
+    fn next(&self) -> Vec<&Imprint<Self::CellData>>;
     fn tick(&mut self) -> TickResult;
     fn is_paused(&self) -> bool;
     fn input_state(&mut self) -> &mut InputState;
+    fn config(&self) -> &Self::Config;
+    // start a fresh game with the given settings, as if chosen from the menu.
+    fn configure(&mut self, config: Self::Config);
+    // save the game in progress, if there is one, so that it can be resumed
+    // from the menu the next time the game is started.
+    fn suspend(&mut self) -> io::Result<()>;
+    fn can_resume(&self) -> bool;
+    // what every game keeps from one run to the next.
+    fn score_table(&self) -> &Leaderboard<'_>;
+    fn statistics(&self) -> &Stats<'_>;
+    fn achievements(&self) -> &Achievements<'_>;
+    // something gone wrong that the player should know about, such as the
+    // scores not being saved.
+    fn warning(&self) -> Option<&str> {
+        self.score_table().warning()
+    }
+    // the score table or initials entry, while one is shown in place of
+    // the game.
+    fn leaderboard(&self) -> Option<&Leaderboard<'_>> {
+        Some(self.score_table()).filter(|s| s.is_open())
+    }
+    // an achievement just earned, while it is being announced.
+    fn achievement(&self) -> Option<&str> {
+        self.achievements().popup()
+    }
+    // the lifetime statistics, while they are shown in place of the game.
+    fn stats(&self) -> Option<&Stats<'_>> {
+        Some(self.statistics()).filter(|s| s.is_open())
+    }
+    // what happened during the last tick, in the order it happened.
+    fn events(&self) -> &[Event];
 }
@@ -0,0 +1,235 @@
+use game::InputState;
+use game::speed::{Motion, CELL};
+use TICK_RATE;
+
+// how long a tick lasts, in milliseconds.
+const TICK_MS: u32 = 1000 / TICK_RATE;
+// moves in a tick that stand for as far as something can go at once.
+pub const INSTANT: u8 = i8::MAX as u8;
+
+// How held directions repeat, in milliseconds, as the player likes them.
+// Anything shorter than a tick is as good as a tick, except where it means
+// more than one move in a tick.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rates {
+    // delayed auto shift: how long a direction is held before it repeats.
+    pub das: u32,
+    // auto repeat rate: how long between moves once it does, or 0 to go as
+    // far as it can at once.
+    pub arr: u32,
+    // how long a held down takes over each row, or 0 to go all the way at
+    // once.
+    pub soft_drop: u32,
+}
+
+impl Default for Rates {
+    // as the games have always done it.
+    fn default() -> Rates {
+        Rates {
+            das: TICK_MS * 3,
+            arr: TICK_MS,
+            soft_drop: TICK_MS,
+        }
+    }
+}
+
+// Turns held directions into moves, tick by tick, the same for every game.
+// It is run on the input before each tick is recorded, so replays need only
+// the moves it came up with, whatever the rates were.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoRepeat {
+    pub rates: Rates,
+    sideways: Held,
+    upright: Held,
+    // whether down was held last tick.
+    falling: bool,
+    fall: Motion,
+}
+
+// The way one pair of directions is held, -1 or 1, and for how many ticks.
+#[derive(Debug, Clone, PartialEq)]
+struct Held {
+    direction: i8,
+    ticks: u32,
+    motion: Motion,
+}
+
+impl AutoRepeat {
+    pub fn new(rates: Rates) -> AutoRepeat {
+        AutoRepeat {
+            rates,
+            sideways: Held::new(),
+            upright: Held::new(),
+            falling: false,
+            fall: Motion::new(),
+        }
+    }
+
+    // works out this tick's moves from what is held. Left wins over right
+    // and up over down when both are, and letting go of one for the other
+    // starts over. Down also falls, at the soft drop rate.
+    pub fn apply(&mut self, input: &mut InputState) {
+        input.shift = self.sideways.moves(input.left, input.right, self.rates);
+        input.climb = self.upright.moves(input.up, input.down, self.rates);
+        input.fall = if !input.down {
+            0
+        } else if !self.falling {
+            self.fall.reset();
+            if self.rates.soft_drop == 0 { INSTANT } else { 1 }
+        } else {
+            repeat(&mut self.fall, self.rates.soft_drop)
+        };
+        self.falling = input.down;
+    }
+}
+
+impl Held {
+    fn new() -> Held {
+        Held {
+            direction: 0,
+            ticks: 0,
+            motion: Motion::new(),
+        }
+    }
+
+    // this tick's moves, less than nothing towards `minus`.
+    fn moves(&mut self, minus: bool, plus: bool, rates: Rates) -> i8 {
+        let direction = if minus {
+            -1
+        } else if plus {
+            1
+        } else {
+            0
+        };
+        if direction != self.direction {
+            self.direction = direction;
+            self.ticks = 0;
+        }
+        if direction == 0 {
+            return 0;
+        }
+        let held = self.ticks;
+        self.ticks = held.saturating_add(1);
+        let moves = if held == 0 {
+            1
+        } else {
+            let elapsed = held.saturating_mul(TICK_MS);
+            if elapsed < rates.das {
+                0
+            } else if elapsed - TICK_MS < rates.das {
+                // the first repeat comes as soon as the delay is up.
+                self.motion.reset();
+                if rates.arr == 0 { INSTANT } else { 1 }
+            } else {
+                repeat(&mut self.motion, rates.arr)
+            }
+        };
+        direction * moves as i8
+    }
+}
+
+// moves in a tick, one every `ms` milliseconds. The speed rounds up, so that
+// a whole number of ticks between moves isn't a fraction short of a cell.
+fn repeat(motion: &mut Motion, ms: u32) -> u8 {
+    if ms == 0 {
+        return INSTANT;
+    }
+    motion.advance((CELL * TICK_MS).div_ceil(ms)).min(INSTANT as u32) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const I: i8 = INSTANT as i8;
+
+    fn rates(das: u32, arr: u32, soft_drop: u32) -> Rates {
+        Rates { das, arr, soft_drop }
+    }
+
+    // the moves for each tick, with left held on the ticks given as true.
+    fn shifts(rates: Rates, held: &[bool]) -> Vec<i8> {
+        let mut repeat = AutoRepeat::new(rates);
+        held.iter().map(|&left| {
+            let mut input = InputState::new();
+            input.left = left;
+            repeat.apply(&mut input);
+            input.shift
+        }).collect()
+    }
+
+    fn falls(rates: Rates, ticks: usize) -> Vec<u8> {
+        let mut repeat = AutoRepeat::new(rates);
+        (0..ticks).map(|_| {
+            let mut input = InputState::new();
+            input.down = true;
+            repeat.apply(&mut input);
+            input.fall
+        }).collect()
+    }
+
+    #[test]
+    fn defaults_move_then_repeat_every_tick_after_three() {
+        assert_eq!(shifts(Rates::default(), &[true; 6]), [-1, 0, 0, -1, -1, -1]);
+    }
+
+    #[test]
+    fn times_between_ticks_round_up_to_a_tick() {
+        let held = [true; 6];
+        // half a tick past one is as good as two.
+        assert_eq!(shifts(rates(TICK_MS * 3 / 2, TICK_MS, TICK_MS), &held), [-1, 0, -1, -1, -1, -1]);
+        // anything under a tick repeats on the very next one.
+        assert_eq!(shifts(rates(1, TICK_MS, TICK_MS), &held), [-1, -1, -1, -1, -1, -1]);
+        // with no delay, a repeat of three ticks moves on every third.
+        assert_eq!(shifts(rates(0, TICK_MS * 3, TICK_MS), &held), [-1, 0, 0, -1, 0, 0]);
+    }
+
+    #[test]
+    fn repeats_under_a_tick_move_more_than_once() {
+        assert_eq!(shifts(rates(0, TICK_MS / 2, TICK_MS), &[true; 4]), [-1, -2, -2, -2]);
+    }
+
+    #[test]
+    fn no_repeat_rate_goes_all_the_way_once_the_delay_is_up() {
+        assert_eq!(shifts(rates(TICK_MS * 2, 0, TICK_MS), &[true; 5]), [-1, 0, -I, -I, -I]);
+    }
+
+    #[test]
+    fn letting_go_during_the_delay_starts_it_over() {
+        let held = [true, true, false, true, true, true, true];
+        assert_eq!(shifts(Rates::default(), &held), [-1, 0, 0, -1, 0, 0, -1]);
+    }
+
+    #[test]
+    fn left_wins_over_right_and_switching_starts_over() {
+        let mut repeat = AutoRepeat::new(Rates::default());
+        let mut moves = Vec::new();
+        for &(left, right) in &[(true, true), (true, false), (false, true), (false, true)] {
+            let mut input = InputState::new();
+            input.left = left;
+            input.right = right;
+            repeat.apply(&mut input);
+            moves.push(input.shift);
+        }
+        assert_eq!(moves, [-1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn up_and_down_repeat_like_left_and_right() {
+        let mut repeat = AutoRepeat::new(Rates::default());
+        let climbs: Vec<_> = (0..5).map(|_| {
+            let mut input = InputState::new();
+            input.up = true;
+            repeat.apply(&mut input);
+            input.climb
+        }).collect();
+        assert_eq!(climbs, [-1, 0, 0, -1, -1]);
+    }
+
+    #[test]
+    fn soft_drop_falls_from_the_first_tick() {
+        assert_eq!(falls(Rates::default(), 4), [1, 1, 1, 1]);
+        assert_eq!(falls(rates(TICK_MS * 3, TICK_MS, TICK_MS * 2), 5), [1, 0, 1, 0, 1]);
+        assert_eq!(falls(rates(TICK_MS * 3, TICK_MS, 0), 3), [INSTANT; 3]);
+    }
+}
@@ -1,24 +1,118 @@
-use std::path::Path;
+use std::io::{self, Read, Write};
 
-mod score_table;
 
-use self::score_table::ScoreTable;
 
-use game::{Game, InputState, TickResult};
+use game::{self, Event, Game, InputState, Options, TickResult};
+use game::achievements::{Achievement, Achievements};
+use game::leaderboard::Leaderboard;
+use game::score_table::Score;
+use game::stats::{self, Kind, Stats};
+use game::speed::{self, Motion};
+use game::saved::SavedGame;
+use persist::{self, Persist};
 
 use imprint::{Imprint, Cell};
+use random::Random;
+use storage::Storage;
+
+use rand::Rng;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 pub const WIDTH: usize = 10;
 pub const HEIGHT: usize = 20;
 
-pub const MAX_LEVEL: u32 = ::FRAMERATE; // should always be <= FRAMERATE
-pub const MAX_ROBOTS: u32 = 20; // should always be <= FRAMERATE
+pub const MAX_LEVEL: u32 = 20;
+pub const MAX_ROBOTS: u32 = 20;
+const SAVE_VERSION: u32 = 3;
+// the shape of the score table in files from before it had a header.
+const LEGACY_SCORES: (usize, usize) = (20, 21);
+
+// lifetime statistics particular to robots.
+const STATS: &[(&str, Kind)] = &[
+    ("SCRAPPED", Kind::Count),
+    ("TELEPORTS", Kind::Count),
+];
+
+// Things worth doing in robots.
+fn achievements<'a>() -> [Achievement<Robots<'a>>; 1] {
+    [
+        // a hundred robots scrapped between one teleport and the next.
+        Achievement {
+            name: "CASCADE",
+            earned: |g| g.cascade.is_some_and(|n| n >= 100),
+        },
+    ]
+}
+
+// How fast the robots close in at each level: slowly at first, since every
+// step they take is one the player has to answer, and at the last levels
+// faster than the ticks, taking a step and a half, then two, each tick.
+const PURSUIT: [u32; MAX_LEVEL as usize] = [
+    speed::every(30),
+    speed::every(26),
+    speed::every(23),
+    speed::every(20),
+    speed::every(18),
+    speed::every(16),
+    speed::every(14),
+    speed::every(12),
+    speed::every(11),
+    speed::every(10),
+    speed::every(9),
+    speed::every(8),
+    speed::every(7),
+    speed::every(6),
+    speed::every(5),
+    speed::every(4),
+    speed::every(3),
+    speed::every(2),
+    speed::CELL * 3 / 2,
+    speed::CELL * 2,
+];
+
+fn pursuit(level: u32) -> u32 {
+    PURSUIT[level as usize]
+}
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Config {
     pub robots: u32,
     pub level: u32,
 }
 
+impl Default for Config {
+    fn default() -> Config {
+        Config { robots: 5, level: 0 }
+    }
+}
+
+impl Options for Config {
+    fn set_option(&mut self, name: &str, value: u32) -> Result<bool, String> {
+        match name {
+            "level" => self.level = game::check_range(name, value, 1, MAX_LEVEL)? - 1,
+            "robots" => self.robots = game::check_range(name, value, 1, MAX_ROBOTS)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl Persist for Config {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u32::<LittleEndian>(self.robots)?;
+        w.write_u32::<LittleEndian>(self.level)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Config> {
+        let robots = r.read_u32::<LittleEndian>()?;
+        let level = r.read_u32::<LittleEndian>()?;
+        if robots == 0 || robots > MAX_ROBOTS || level >= MAX_LEVEL {
+            return Err(persist::invalid_data("robots settings out of range"));
+        }
+        Ok(Config { robots, level })
+    }
+}
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Status {
@@ -30,20 +124,67 @@ pub enum Status {
     Teleporting((usize, usize)),
 }
 
+impl Persist for Status {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Status::Active => w.write_u8(0),
+            Status::Paused => w.write_u8(1),
+            Status::Raising(f) => {
+                w.write_u8(2)?;
+                f.save(w)
+            }
+            Status::Lowering(f) => {
+                w.write_u8(3)?;
+                f.save(w)
+            }
+            Status::Menu(f) => {
+                w.write_u8(4)?;
+                f.save(w)
+            }
+            Status::Teleporting(p) => {
+                w.write_u8(5)?;
+                p.save(w)
+            }
+        }
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Status> {
+        Ok(match r.read_u8()? {
+            0 => Status::Active,
+            1 => Status::Paused,
+            2 => Status::Raising(usize::load(r)?),
+            3 => Status::Lowering(usize::load(r)?),
+            4 => Status::Menu(u32::load(r)?),
+            5 => Status::Teleporting(<(usize, usize)>::load(r)?),
+            _ => return Err(persist::invalid_data("bad robots status")),
+        })
+    }
+}
+
 
 pub struct Robots<'a> {
     pub config: Config,
     pub status: Status,
     pub anim_tick: u32,
     pub position: (usize, usize),
-    movement_tick: u32,
+    movement: Motion,
     input: InputState,
     board: Imprint<CellData>,
     pub robots: Vec<(usize, usize)>,
     pub teleports: u32,
     points: u32,
-    score_table: ScoreTable<'a>,
-    speed: u32,
+    scores: Leaderboard<'a>,
+    stats: Stats<'a>,
+    achievements: Achievements<'a>,
+    scrapped: u32,
+    // robots scrapped since the last teleport, until the robots move
+    // without any more being scrapped.
+    cascade: Option<u32>,
+    // what happened during the current tick.
+    events: Vec<Event>,
+    level: u32,
+    rng: Random,
+    saved: SavedGame<'a>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -52,45 +193,136 @@ pub enum CellData {
     Robot,
 }
 
+impl Persist for CellData {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(match *self {
+            CellData::ScrapHeap => 0,
+            CellData::Robot => 1,
+        })
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<CellData> {
+        Ok(match r.read_u8()? {
+            0 => CellData::ScrapHeap,
+            1 => CellData::Robot,
+            _ => return Err(persist::invalid_data("bad robots cell")),
+        })
+    }
+}
+
 impl<'a> Robots<'a> {
-    pub fn new(filename: &'a Path) -> ::std::io::Result<Self> {
+    pub fn new(storage: &'a dyn Storage, key: &'a str, seed: u64) -> Self {
         let mut g = Robots {
-            config: Config {
-                robots: 5,
-                level: 0,
-            },
+            config: Config::default(),
             status: Status::Menu(0),
             board: Imprint::empty(WIDTH, HEIGHT),
-            movement_tick: 0,
-            speed: MAX_LEVEL - 9,
+            movement: Motion::new(),
+            level: 0,
             position: (WIDTH / 2, HEIGHT / 2),
             robots: Vec::new(),
             points: 0,
             teleports: 8,
-            score_table: ScoreTable::new(filename)?,
+            scores: Leaderboard::new(storage, key, (MAX_LEVEL as usize, MAX_ROBOTS as usize + 1), LEGACY_SCORES, "SCRAP", &[]),
+            stats: Stats::new(storage, key, STATS),
+            achievements: Achievements::new(storage, key),
+            scrapped: 0,
+            cascade: None,
+            events: Vec::new(),
             input: InputState::new(),
             anim_tick: 0,
+            rng: Random::new(seed),
+            saved: SavedGame::new(storage, key, SAVE_VERSION),
         };
         g.new_game();
-        Ok(g)
+        g
     }
 
     fn new_game(&mut self) {
         self.board = Imprint::empty(WIDTH, HEIGHT);
-        self.score_table
-            .update_scores(&self.config, self.points)
-            .unwrap();
+        let score = Score::new(self.points, self.level, self.scrapped, 0);
+        let high = self.scores.finish(&mut self.input, self.config.level, self.config.robots, score);
+        if high {
+            self.events.push(Event::HighScore(self.points));
+        }
+        self.stats.save();
+        self.scrapped = 0;
+        self.cascade = None;
         self.position = (WIDTH / 2, HEIGHT / 2);
         self.points = 0;
         self.teleports = 8;
         self.robots = Vec::new();
-        self.speed = MAX_LEVEL - self.config.level;
+        self.level = self.config.level;
+        self.movement.reset();
         for _ in 0..self.config.robots {
             let p = self.random_border_spot();
             self.robots.push(p);
             self.place_robot(p);
         }
     }
+    fn save_game(&self) -> io::Result<()> {
+        let mut file = self.saved.start()?;
+        self.config.save(&mut file)?;
+        match self.status {
+            Status::Active => Status::Paused,
+            ref s => s.clone(),
+        }.save(&mut file)?;
+        self.anim_tick.save(&mut file)?;
+        self.position.save(&mut file)?;
+        self.movement.save(&mut file)?;
+        self.board.save(&mut file)?;
+        self.robots.save(&mut file)?;
+        self.teleports.save(&mut file)?;
+        self.points.save(&mut file)?;
+        self.scrapped.save(&mut file)?;
+        self.level.save(&mut file)?;
+        self.rng.save(&mut file)?;
+        self.saved.write(&file)
+    }
+
+    fn resume_game(&mut self) -> io::Result<()> {
+        let bytes = self.saved.read()?;
+        let mut file = &bytes[..];
+        let config = Config::load(&mut file)?;
+        let status = Status::load(&mut file)?;
+        let anim_tick = u32::load(&mut file)?;
+        let position = <(usize, usize)>::load(&mut file)?;
+        let movement = Motion::load(&mut file)?;
+        let board = Imprint::load(&mut file)?;
+        let robots: Vec<(usize, usize)> = Vec::load(&mut file)?;
+        let teleports = u32::load(&mut file)?;
+        let points = u32::load(&mut file)?;
+        let scrapped = u32::load(&mut file)?;
+        let level = u32::load(&mut file)?;
+        let rng = Random::load(&mut file)?;
+        let on_board = |&(x, y): &(usize, usize)| x < WIDTH && y < HEIGHT;
+        // only what suspend writes, with the animation within what tick counts.
+        let status_ok = match status {
+            Status::Paused => anim_tick < 4,
+            Status::Teleporting(p) => on_board(&p) && anim_tick < 8,
+            _ => false,
+        };
+        if board.size() != (WIDTH, HEIGHT) || level >= MAX_LEVEL ||
+            !on_board(&position) || !robots.iter().all(on_board) || !status_ok
+        {
+            return Err(persist::invalid_data("saved robots game is inconsistent"));
+        }
+        self.saved.remove()?;
+        self.config = config;
+        self.status = status;
+        self.anim_tick = anim_tick;
+        self.position = position;
+        self.movement = movement;
+        self.board = board;
+        self.robots = robots;
+        self.teleports = teleports;
+        self.points = points;
+        self.scrapped = scrapped;
+        self.cascade = None;
+        self.level = level;
+        self.rng = rng;
+        Ok(())
+    }
+
     fn place_robot(&mut self, p: (usize, usize)) {
         if self.board[p].is_empty() {
             self.board[p] = Cell::Filled(CellData::Robot);
@@ -109,24 +341,24 @@ impl<'a> Robots<'a> {
         self.robots.retain(|&p| {
             board[p] != Cell::Filled(CellData::ScrapHeap)
         });
-        return x - self.robots.len();
+        x - self.robots.len()
     }
-    fn random_border_spot(&self) -> (usize, usize) {
-        let xc = ::rand::random::<u32>() as usize % WIDTH;
-        let yc = ::rand::random::<u32>() as usize % HEIGHT;
-        let b = ::rand::random::<u32>() % 4;
+    fn random_border_spot(&mut self) -> (usize, usize) {
+        let xc = self.rng.gen::<u32>() as usize % WIDTH;
+        let yc = self.rng.gen::<u32>() as usize % HEIGHT;
+        let b = self.rng.gen::<u32>() % 4;
         let (x, y) = match b {
             0 => (xc, 0),
             1 => (xc, HEIGHT - 1),
             2 => (0, yc),
             _ => (WIDTH - 1, yc),
         };
-        return (x, y);
+        (x, y)
     }
 
-    fn random_free_spot(&self) -> (usize, usize) {
-        let x = ::rand::random::<u32>() as usize % WIDTH;
-        let y = ::rand::random::<u32>() as usize % HEIGHT;
+    fn random_free_spot(&mut self) -> (usize, usize) {
+        let x = self.rng.gen::<u32>() as usize % WIDTH;
+        let y = self.rng.gen::<u32>() as usize % HEIGHT;
         if !self.board[(x, y)].is_empty() || self.position == (x, y) {
             for xo in 0..WIDTH {
                 for yo in 0..HEIGHT {
@@ -137,7 +369,7 @@ impl<'a> Robots<'a> {
                 }
             }
         }
-        return (x, y);
+        (x, y)
     }
 
     fn towards((fx, fy): (usize, usize), (tx, ty): (usize, usize)) -> (usize, usize) {
@@ -158,11 +390,40 @@ impl<'a> Robots<'a> {
         (x, y)
     }
 
+    // take up to `moves` steps towards a spot, stopping at the edge or if
+    // caught.
+    fn walk(&mut self, to: (usize, usize), moves: u8) {
+        for _ in 0..moves {
+            let next = Robots::towards(self.position, to);
+            if next == self.position || self.status != Status::Active {
+                break;
+            }
+            self.position = next;
+            self.check_safety();
+        }
+    }
+
     fn check_safety(&mut self) {
-        if !self.board[self.position].is_empty() {
+        // once caught, moving again in the same tick doesn't end it twice.
+        if self.status == Status::Active && !self.board[self.position].is_empty() {
+            self.events.push(Event::GameOver);
             self.status = Status::Raising(0);
         }
     }
+    // takes robots turned to scrap off the list, and scores them.
+    fn count_scrapped(&mut self) {
+        let scrapped = self.validate_robots() as u32;
+        self.scrapped += scrapped;
+        self.stats.add("SCRAPPED", scrapped as u64);
+        if scrapped > 0 {
+            self.events.push(Event::RobotsScrapped(scrapped));
+        }
+        if let Some(ref mut n) = self.cascade {
+            *n += scrapped;
+        }
+        self.points += scrapped * (self.current_level() + 1);
+    }
+
     fn obliterate(&mut self, x0 : usize, y0 : usize, x1 : usize, y1 : usize) {
         for x in x0..x1+1 {
             for y in y0..y1+1 {
@@ -171,7 +432,7 @@ impl<'a> Robots<'a> {
                 }
             }
         }
-        self.points += self.validate_robots() as u32 * (self.current_level() + 1);
+        self.count_scrapped();
     }
 
     fn advance(&mut self) {
@@ -188,7 +449,7 @@ impl<'a> Robots<'a> {
                 self.board[*p] = Cell::Filled(CellData::ScrapHeap);
             }
         }
-        self.points += self.validate_robots() as u32 * (self.current_level() + 1);
+        self.count_scrapped();
         self.check_safety();
         if self.robots.len() < self.config.robots as usize {
             let p = self.random_border_spot();
@@ -199,23 +460,37 @@ impl<'a> Robots<'a> {
 }
 
 impl<'a> Game for Robots<'a> {
+    type Config = Config;
     type CellData = CellData;
     fn current_level(&self) -> u32 {
-        MAX_LEVEL - self.speed
+        self.level
     }
     fn score(&self) -> u32 {
         self.points
     }
     fn top_score(&self) -> u32 {
-        self.score_table.get_top_score(&self.config)
+        self.scores.get_top_score(self.config.level, self.config.robots)
     }
     fn board(&self) -> &Imprint<CellData> {
         &self.board
     }
-    fn next(&self) -> Option<&Imprint<CellData>> {
-        None
+    fn next(&self) -> Vec<&Imprint<CellData>> {
+        Vec::new()
     }
     fn tick(&mut self) -> TickResult {
+        self.events.clear();
+        self.achievements.tick();
+        if self.scores.is_open() {
+            self.scores.tick(&mut self.input);
+            return TickResult::Continue;
+        }
+        if self.stats.is_open() {
+            self.stats.tick(&mut self.input);
+            return TickResult::Continue;
+        }
+        if matches!(self.status, Status::Active | Status::Teleporting(_)) {
+            self.stats.add(stats::TIME, 1);
+        }
         match self.status {
             Status::Teleporting(p) => {
                 let (x1, y1) = p;
@@ -228,8 +503,8 @@ impl<'a> Game for Robots<'a> {
                         if y0 == HEIGHT - 1 { y0 } else { y0 + 1 },
                     );
                     self.obliterate(
-                        if x1 == 1 { x1 } else { x1 - 1 },
-                        if y1 == 1 { y1 } else { y1 - 1 },
+                        if x1 == 0 { x1 } else { x1 - 1 },
+                        if y1 == 0 { y1 } else { y1 - 1 },
                         if x1 == WIDTH - 1 { x1 } else { x1 + 1 },
                         if y1 == HEIGHT - 1 { y1 } else { y1 + 1 },
                     );
@@ -244,40 +519,33 @@ impl<'a> Game for Robots<'a> {
                     self.status = Status::Paused;
                     self.input.escape = false;
                 } else {
-                    if self.input.left {
-                        self.input.left = false;
-                        self.position = Robots::towards(self.position, (0, self.position.1));
-                        self.check_safety();
-                    }
-                    if self.input.right {
-                        self.input.right = false;
-                        self.position =
-                            Robots::towards(self.position, (WIDTH - 1, self.position.1));
-                        self.check_safety();
-                    }
-                    if self.input.up {
-                        self.input.up = false;
-                        self.position = Robots::towards(self.position, (self.position.0, 0));
-                        self.check_safety();
+                    let (shift, climb) = (self.input.shift, self.input.climb);
+                    let x = if shift < 0 { 0 } else { WIDTH - 1 };
+                    self.walk((x, self.position.1), shift.unsigned_abs());
+                    let y = if climb < 0 { 0 } else { HEIGHT - 1 };
+                    self.walk((self.position.0, y), climb.unsigned_abs());
+                    if (self.input.button_a || self.input.button_b) && self.teleports > 0 {
+                        self.teleports -= 1;
+                        self.stats.add("TELEPORTS", 1);
+                        self.events.push(Event::Teleport);
+                        self.cascade = Some(0);
+                        self.status = Status::Teleporting(self.random_free_spot());
+                        self.anim_tick = 0;
+                        self.input.button_a = false;
                     }
-                    if self.input.down {
-                        self.input.down = false;
-                        self.position =
-                            Robots::towards(self.position, (self.position.0, HEIGHT - 1));
-                        self.check_safety();
-                    }
-                    if self.input.button_a || self.input.button_b {
-                        if self.teleports > 0 {
-                            self.teleports -= 1;
-                            self.status = Status::Teleporting(self.random_free_spot());
-                            self.anim_tick = 0;
-                            self.input.button_a = false;
+                    let (scrapped, mut moved) = (self.scrapped, false);
+                    for _ in 0..self.movement.advance(pursuit(self.level)) {
+                        if self.status != Status::Active {
+                            break;
                         }
-                    }
-                    if self.movement_tick == 0 {
                         self.advance();
+                        moved = true;
+                    }
+                    // a tick's moves that scrap nothing end the chain from
+                    // the last teleport.
+                    if moved && self.scrapped == scrapped {
+                        self.cascade = None;
                     }
-                    self.movement_tick = (self.movement_tick + 1) % self.speed;
                     self.anim_tick = (self.anim_tick + 1) % 4;
                 }
             }
@@ -289,6 +557,7 @@ impl<'a> Game for Robots<'a> {
                     self.status = Status::Active;
                 } else if self.input.escape {
                     self.input.escape = false;
+                    self.events.push(Event::GameOver);
                     self.status = Status::Raising(0);
                 }
             }
@@ -321,47 +590,95 @@ impl<'a> Game for Robots<'a> {
                     self.input.escape = false;
                     return TickResult::PrevGame;
                 }
-                if self.input.right {
-                    self.input.right = false;
-                    if self.config.robots < MAX_ROBOTS {
-                        self.config.robots += 1;
-                        self.new_game();
-                    }
+                // a step a tick, however fast the directions repeat.
+                if self.input.shift > 0 && self.config.robots < MAX_ROBOTS {
+                    self.config.robots += 1;
+                    self.new_game();
                 }
-                if self.input.left {
-                    self.input.left = false;
-                    if self.config.robots > 1 {
-                        self.config.robots -= 1;
-                        self.new_game();
-                    }
+                if self.input.shift < 0 && self.config.robots > 1 {
+                    self.config.robots -= 1;
+                    self.new_game();
                 }
                 if self.input.drop {
                     self.input.drop = false;
                     self.status = Status::Active;
+                    self.stats.add(stats::GAMES, 1);
                 }
-                if self.input.up {
-                    self.input.up = false;
-                    if self.config.level < MAX_LEVEL - 1 {
-                        self.config.level += 1;
-                        self.speed = MAX_LEVEL - self.config.level;
-                    }
+                if self.input.stats {
+                    self.input.stats = false;
+                    self.stats.show();
+                }
+                if self.input.button_b {
+                    self.input.button_b = false;
+                    self.scores.browse(self.config.level, self.config.robots);
                 }
-                if self.input.down {
-                    self.input.down = false;
-                    if self.config.level > 0 {
-                        self.config.level -= 1;
-                        self.speed = MAX_LEVEL - self.config.level;
+                if self.input.button_a {
+                    self.input.button_a = false;
+                    if self.saved.exists() {
+                        if let Err(e) = self.resume_game() {
+                            eprintln!("could not resume the saved game: {}", e);
+                        }
                     }
                 }
+                if self.input.climb < 0 && self.config.level < MAX_LEVEL - 1 {
+                    self.config.level += 1;
+                    self.level = self.config.level;
+                }
+                if self.input.climb > 0 && self.config.level > 0 {
+                    self.config.level -= 1;
+                    self.level = self.config.level;
+                }
             }
         }
+        let earned = self.achievements.newly_earned(self, &achievements());
+        self.achievements.earn(&earned);
         TickResult::Continue
     }
     fn is_paused(&self) -> bool {
-        return self.status == Status::Paused;
+        self.status == Status::Paused
     }
 
     fn input_state(&mut self) -> &mut InputState {
         &mut self.input
     }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+        self.new_game();
+    }
+
+    fn suspend(&mut self) -> io::Result<()> {
+        self.stats.save();
+        match self.status {
+            Status::Active | Status::Paused | Status::Teleporting(_) => self.save_game(),
+            _ => Ok(()),
+        }
+    }
+
+    fn can_resume(&self) -> bool {
+        match self.status {
+            Status::Menu(_) => self.saved.exists(),
+            _ => false,
+        }
+    }
+
+    fn score_table(&self) -> &Leaderboard<'_> {
+        &self.scores
+    }
+
+    fn statistics(&self) -> &Stats<'_> {
+        &self.stats
+    }
+
+    fn achievements(&self) -> &Achievements<'_> {
+        &self.achievements
+    }
+
+    fn events(&self) -> &[Event] {
+        &self.events
+    }
 }
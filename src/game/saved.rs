@@ -0,0 +1,64 @@
+use std::io;
+
+use persist;
+use storage::Storage;
+
+// saved games start with this, followed by a version number particular to
+// the game that wrote them.
+const MAGIC: &[u8; 4] = b"BGSV";
+
+// A game left part way through, kept for the menu to offer to take up again
+// the next time the game is started. Only one is kept for each game, and
+// it is gone once taken up.
+pub struct SavedGame<'a> {
+    storage: &'a dyn Storage,
+    key: String,
+    version: u32,
+    // whether there is one still to offer.
+    resumable: bool,
+}
+
+impl<'a> SavedGame<'a> {
+    pub fn new(storage: &'a dyn Storage, key: &str, version: u32) -> SavedGame<'a> {
+        let key = format!("{}.save", key);
+        SavedGame {
+            resumable: matches!(storage.read(&key), Ok(Some(_))),
+            storage,
+            key,
+            version,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.resumable
+    }
+
+    // a file to write the game to, with the header already written.
+    pub fn start(&self) -> io::Result<Vec<u8>> {
+        let mut file = Vec::new();
+        persist::write_header(&mut file, MAGIC, self.version)?;
+        Ok(file)
+    }
+
+    pub fn write(&self, file: &[u8]) -> io::Result<()> {
+        self.storage.write(&self.key, file)
+    }
+
+    // what the game wrote after the header. It is offered only the once,
+    // whether or not the game can make anything of it.
+    pub fn read(&mut self) -> io::Result<Vec<u8>> {
+        self.resumable = false;
+        let bytes = self.storage.read(&self.key)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no saved game"))?;
+        let mut file = &bytes[..];
+        if persist::read_header(&mut file, MAGIC)? != self.version {
+            return Err(persist::invalid_data("unsupported saved game version"));
+        }
+        Ok(file.to_vec())
+    }
+
+    // the game has been taken up, and is not to be taken up again.
+    pub fn remove(&self) -> io::Result<()> {
+        self.storage.remove(&self.key)
+    }
+}
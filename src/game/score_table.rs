@@ -0,0 +1,393 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use persist::{self, Persist};
+use storage::{self, Storage};
+
+const MAGIC: &[u8; 4] = b"BGHS";
+const VERSION: u32 = 3;
+pub const TABLE_SIZE: usize = 10;
+
+// One finished game on a score table.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Score {
+    pub initials: [u8; 3],
+    // seconds since the unix epoch, or 0 if not known.
+    pub date: u64,
+    pub points: u32,
+    pub level: u32,
+    // something else worth knowing about the game, particular to each one:
+    // lines cleared in tetris, say.
+    pub detail: u32,
+    // which of the game's modes it was played in, such as the randomizer in
+    // tetris; 0 for games with only the one.
+    pub mode: u32,
+}
+
+impl Score {
+    // a game just finished, still to be given initials and a date.
+    pub fn new(points: u32, level: u32, detail: u32, mode: u32) -> Score {
+        Score {
+            initials: *b"---",
+            date: 0,
+            points,
+            level,
+            detail,
+            mode,
+        }
+    }
+
+    // the date as a year, month and day.
+    pub fn day(&self) -> (i64, u32, u32) {
+        // days since the epoch to a civil date, after Howard Hinnant.
+        let z = (self.date / 86400) as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let m = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+        (y, m, d)
+    }
+}
+
+impl Persist for Score {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.initials)?;
+        self.date.save(w)?;
+        self.points.save(w)?;
+        self.level.save(w)?;
+        self.detail.save(w)?;
+        self.mode.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Score> {
+        let mut score = read_v2_score(r)?;
+        score.mode = u32::load(r)?;
+        Ok(score)
+    }
+}
+
+// a score from a version 2 table, from before they kept the mode.
+fn read_v2_score<R: Read>(r: &mut R) -> io::Result<Score> {
+    let mut initials = [0; 3];
+    r.read_exact(&mut initials)?;
+    if !initials.iter().all(|c| c.is_ascii_uppercase() || *c == b'-') {
+        return Err(persist::invalid_data("bad initials"));
+    }
+    Ok(Score {
+        initials,
+        date: u64::load(r)?,
+        points: u32::load(r)?,
+        level: u32::load(r)?,
+        detail: u32::load(r)?,
+        mode: 0,
+    })
+}
+
+// The best scores for each level and variant of a game, where the variant is
+// whatever else its menu lets you choose: the starting height in tetris, the
+// field in snake or the number of robots. Files give their own shape after
+// the header, so a table can be read back even after the game has gained or
+// lost levels or variants since it was written.
+pub struct ScoreTable<'a> {
+    levels: usize,
+    variants: usize,
+    scores: Vec<Vec<Score>>,
+    storage: &'a dyn Storage,
+    key: &'a str,
+}
+
+impl<'a> ScoreTable<'a> {
+    pub fn empty(storage: &'a dyn Storage, key: &'a str, shape: (usize, usize)) -> ScoreTable<'a> {
+        ScoreTable {
+            levels: shape.0,
+            variants: shape.1,
+            scores: vec![Vec::new(); shape.0 * shape.1],
+            storage,
+            key,
+        }
+    }
+
+    // `legacy` is the shape the game's table had before files had a header;
+    // one of those, or of an older version, is converted and written back in
+    // the current format. A table that can't be read is passed over for its
+    // backup.
+    pub fn new(
+        storage: &'a dyn Storage,
+        key: &'a str,
+        shape: (usize, usize),
+        legacy: (usize, usize),
+    ) -> io::Result<ScoreTable<'a>> {
+        let read = storage::read_with_backup(storage, key, |bytes| {
+            let mut it = ScoreTable::empty(storage, key, shape);
+            let converted = it.read_scores(bytes, legacy)?;
+            Ok((it, converted))
+        })?;
+        match read {
+            None => Ok(ScoreTable::empty(storage, key, shape)),
+            Some((it, converted)) => {
+                if converted {
+                    it.save_scores()?;
+                }
+                Ok(it)
+            }
+        }
+    }
+
+    // true if the file was in an older format.
+    fn read_scores(&mut self, bytes: &[u8], legacy: (usize, usize)) -> io::Result<bool> {
+        if !bytes.starts_with(MAGIC) {
+            if bytes.len() != legacy.0 * legacy.1 * 4 {
+                return Err(persist::invalid_data("unrecognised score table"));
+            }
+            self.read_top_scores(&mut &bytes[..], legacy)?;
+            return Ok(true);
+        }
+        let r = &mut &bytes[..];
+        let version = persist::read_header(r, MAGIC)?;
+        let levels = r.read_u32::<LittleEndian>()? as usize;
+        let variants = r.read_u32::<LittleEndian>()? as usize;
+        match version {
+            1 => {
+                self.read_top_scores(r, (levels, variants))?;
+                Ok(true)
+            }
+            2 | VERSION => {
+                for i in 0..levels {
+                    for j in 0..variants {
+                        let scores = if version == 2 {
+                            let n = persist::read_len(r)?;
+                            (0..n).map(|_| read_v2_score(r)).collect::<io::Result<_>>()?
+                        } else {
+                            Vec::load(r)?
+                        };
+                        if i < self.levels && j < self.variants {
+                            self.scores[i * self.variants + j] = scores;
+                        }
+                    }
+                }
+                Ok(version != VERSION)
+            }
+            _ => Err(persist::invalid_data("unsupported score table version")),
+        }
+    }
+
+    // older tables only kept the best score, with nothing else known about it.
+    fn read_top_scores<R: Read>(&mut self, r: &mut R, shape: (usize, usize)) -> io::Result<()> {
+        for i in 0..shape.0 {
+            for j in 0..shape.1 {
+                let points = r.read_u32::<LittleEndian>()?;
+                if i < self.levels && j < self.variants && points > 0 {
+                    self.scores[i * self.variants + j] = vec![Score::new(points, i as u32, 0, 0)];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save_scores(&self) -> io::Result<()> {
+        let mut w = Vec::new();
+        persist::write_header(&mut w, MAGIC, VERSION)?;
+        w.write_u32::<LittleEndian>(self.levels as u32)?;
+        w.write_u32::<LittleEndian>(self.variants as u32)?;
+        for scores in &self.scores {
+            scores.save(&mut w)?;
+        }
+        storage::write_with_backup(self.storage, self.key, &w)
+    }
+
+    pub fn key(&self) -> &str {
+        self.key
+    }
+
+    // why the table won't last, if the storage it is kept in won't.
+    pub fn warning(&self) -> Option<&'static str> {
+        self.storage.warning()
+    }
+
+    // best first.
+    pub fn scores(&self, level: u32, variant: u32) -> &[Score] {
+        &self.scores[level as usize * self.variants + variant as usize]
+    }
+
+    pub fn get_top_score(&self, level: u32, variant: u32) -> u32 {
+        self.scores(level, variant).first().map_or(0, |s| s.points)
+    }
+
+    pub fn qualifies(&self, level: u32, variant: u32, points: u32) -> bool {
+        let scores = self.scores(level, variant);
+        points > 0 && (scores.len() < TABLE_SIZE || scores[TABLE_SIZE - 1].points < points)
+    }
+
+    // returns the score's place in the table, if it made it in at all. The
+    // table is only changed in memory until save_scores is called.
+    pub fn update_scores(&mut self, level: u32, variant: u32, score: Score) -> Option<usize> {
+        if !self.qualifies(level, variant, score.points) {
+            return None;
+        }
+        let scores = &mut self.scores[level as usize * self.variants + variant as usize];
+        // ties go to whoever got there first.
+        let place = scores.iter().position(|s| s.points < score.points).unwrap_or(scores.len());
+        scores.insert(place, score);
+        scores.truncate(TABLE_SIZE);
+        Some(place)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::MemoryStorage;
+
+    const KEY: &str = "scores";
+
+    fn grid(points: &[u32]) -> Vec<u8> {
+        let mut w = Vec::new();
+        for &p in points {
+            w.write_u32::<LittleEndian>(p).unwrap();
+        }
+        w
+    }
+
+    fn header(version: u32, shape: (u32, u32)) -> Vec<u8> {
+        let mut w = Vec::new();
+        persist::write_header(&mut w, MAGIC, version).unwrap();
+        w.write_u32::<LittleEndian>(shape.0).unwrap();
+        w.write_u32::<LittleEndian>(shape.1).unwrap();
+        w
+    }
+
+    // the table is in the current format once it has been read.
+    fn assert_rewritten(storage: &MemoryStorage) {
+        let bytes = storage.read(KEY).unwrap().unwrap();
+        assert_eq!(persist::read_header(&mut &bytes[..], MAGIC).unwrap(), VERSION);
+    }
+
+    #[test]
+    fn legacy_grids_are_converted() {
+        let storage = MemoryStorage::new();
+        storage.write(KEY, &grid(&[10, 0, 30, 40, 50, 60])).unwrap();
+        // the game has gained a level since.
+        let table = ScoreTable::new(&storage, KEY, (3, 2), (3, 2)).unwrap();
+        assert_eq!(table.scores(0, 0), &[Score::new(10, 0, 0, 0)][..]);
+        assert!(table.scores(0, 1).is_empty());
+        assert_eq!(table.get_top_score(2, 1), 60);
+        assert_rewritten(&storage);
+        let again = ScoreTable::new(&storage, KEY, (3, 2), (3, 2)).unwrap();
+        assert_eq!(again.get_top_score(1, 0), 30);
+    }
+
+    #[test]
+    fn legacy_grids_of_the_wrong_size_are_refused() {
+        let storage = MemoryStorage::new();
+        storage.write(KEY, &grid(&[10, 20, 30])).unwrap();
+        assert!(ScoreTable::new(&storage, KEY, (2, 2), (2, 2)).is_err());
+    }
+
+    #[test]
+    fn version_1_tables_are_converted_to_the_games_shape() {
+        let storage = MemoryStorage::new();
+        let mut bytes = header(1, (2, 3));
+        bytes.extend(grid(&[1, 2, 3, 4, 5, 6]));
+        storage.write(KEY, &bytes).unwrap();
+        // written with more variants than the game has now.
+        let table = ScoreTable::new(&storage, KEY, (2, 2), (0, 0)).unwrap();
+        assert_eq!(table.get_top_score(0, 1), 2);
+        assert_eq!(table.get_top_score(1, 0), 4);
+        assert_eq!(table.get_top_score(1, 1), 5);
+        assert_rewritten(&storage);
+    }
+
+    #[test]
+    fn version_2_tables_are_converted_with_no_mode() {
+        let storage = MemoryStorage::new();
+        let mut bytes = header(2, (1, 2));
+        let score = Score { initials: *b"ABC", date: 86400, points: 7, level: 0, detail: 3, mode: 0 };
+        for n in 0..2u32 {
+            bytes.write_u32::<LittleEndian>(n).unwrap();
+            for _ in 0..n {
+                // as now, but for the mode at the end.
+                score.save(&mut bytes).unwrap();
+                bytes.truncate(bytes.len() - 4);
+            }
+        }
+        storage.write(KEY, &bytes).unwrap();
+        let table = ScoreTable::new(&storage, KEY, (1, 2), (0, 0)).unwrap();
+        assert!(table.scores(0, 0).is_empty());
+        assert_eq!(table.scores(0, 1), &[score][..]);
+        assert_rewritten(&storage);
+        let again = ScoreTable::new(&storage, KEY, (1, 2), (0, 0)).unwrap();
+        assert_eq!(again.scores(0, 1), &[score][..]);
+    }
+
+    #[test]
+    fn scores_go_in_best_first_with_ties_to_the_earlier() {
+        let storage = MemoryStorage::new();
+        let mut table = ScoreTable::empty(&storage, KEY, (1, 1));
+        assert_eq!(table.update_scores(0, 0, Score::new(20, 0, 1, 0)), Some(0));
+        assert_eq!(table.update_scores(0, 0, Score::new(30, 0, 2, 0)), Some(0));
+        assert_eq!(table.update_scores(0, 0, Score::new(20, 0, 3, 0)), Some(2));
+        assert_eq!(table.update_scores(0, 0, Score::new(0, 0, 4, 0)), None);
+        let details: Vec<_> = table.scores(0, 0).iter().map(|s| s.detail).collect();
+        assert_eq!(details, vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn only_the_best_ten_are_kept() {
+        let storage = MemoryStorage::new();
+        let mut table = ScoreTable::empty(&storage, KEY, (1, 1));
+        for points in 1..=TABLE_SIZE as u32 {
+            assert!(table.update_scores(0, 0, Score::new(points * 10, 0, 0, 0)).is_some());
+        }
+        assert!(!table.qualifies(0, 0, 10));
+        assert_eq!(table.update_scores(0, 0, Score::new(10, 0, 0, 0)), None);
+        assert_eq!(table.update_scores(0, 0, Score::new(15, 0, 0, 0)), Some(TABLE_SIZE - 1));
+        let points: Vec<_> = table.scores(0, 0).iter().map(|s| s.points).collect();
+        assert_eq!(points, vec![100, 90, 80, 70, 60, 50, 40, 30, 20, 15]);
+        table.save_scores().unwrap();
+        let again = ScoreTable::new(&storage, KEY, (1, 1), (0, 0)).unwrap();
+        assert_eq!(again.scores(0, 0), table.scores(0, 0));
+    }
+
+    #[test]
+    fn a_corrupt_table_falls_back_to_its_backup() {
+        let storage = MemoryStorage::new();
+        let mut table = ScoreTable::empty(&storage, KEY, (1, 1));
+        table.update_scores(0, 0, Score::new(10, 0, 0, 0));
+        table.save_scores().unwrap();
+        table.update_scores(0, 0, Score::new(20, 0, 0, 0));
+        table.save_scores().unwrap();
+        let good = storage.read("scores.bak").unwrap().unwrap();
+        // cut short as if the last write had been interrupted.
+        let mut bytes = storage.read(KEY).unwrap().unwrap();
+        bytes.truncate(bytes.len() - 1);
+        storage.write(KEY, &bytes).unwrap();
+
+        let table = ScoreTable::new(&storage, KEY, (1, 1), (0, 0)).unwrap();
+        assert_eq!(table.get_top_score(0, 0), 10);
+        assert_eq!(table.scores(0, 0).len(), 1);
+        // and the backup is put back in its place.
+        assert_eq!(storage.read(KEY).unwrap(), Some(good));
+    }
+
+    #[test]
+    fn a_corrupt_table_with_no_backup_is_an_error() {
+        let storage = MemoryStorage::new();
+        storage.write(KEY, b"BGHS").unwrap();
+        assert!(ScoreTable::new(&storage, KEY, (1, 1), (0, 0)).is_err());
+        storage.remove(KEY).unwrap();
+        let table = ScoreTable::new(&storage, KEY, (1, 1), (0, 0)).unwrap();
+        assert!(table.scores(0, 0).is_empty());
+    }
+
+    #[test]
+    fn unknown_versions_are_refused() {
+        let storage = MemoryStorage::new();
+        storage.write(KEY, &header(VERSION + 1, (1, 1))).unwrap();
+        assert!(ScoreTable::new(&storage, KEY, (1, 1), (1, 1)).is_err());
+    }
+}
@@ -74,5 +74,5 @@ pub fn field(n: usize) -> Imprint<CellData> {
                 Cell::Filled(CellData::Wall)),
               ];
     }
-    return FIELDS[n].clone();
+    FIELDS[n].clone()
 }
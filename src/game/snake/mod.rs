@@ -1,24 +1,69 @@
-use std::path::Path;
+use std::io::{self, Read, Write};
 
-mod score_table;
 mod fields;
 
-use self::score_table::ScoreTable;
+pub use self::fields::MAX_FIELDS;
 
-use game::{Game, InputState, TickResult};
+use game::{self, Event, Game, InputState, Options, TickResult};
+use game::achievements::{Achievement, Achievements};
+use game::leaderboard::Leaderboard;
+use game::score_table::Score;
+use game::stats::{self, Kind, Stats};
+use game::speed::{self, Motion};
+use game::saved::SavedGame;
+use persist::{self, Persist};
 
 use imprint::{Imprint, Cell};
+use random::Random;
+use storage::Storage;
+
+use rand::Rng;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 pub const WIDTH: usize = 10;
 pub const HEIGHT: usize = 20;
 
-pub const MAX_LEVEL: u32 = ::FRAMERATE; // should always be <= FRAMERATE
+pub const MAX_LEVEL: u32 = 20;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Config {
     pub field: u32,
     pub level: u32,
 }
 
+impl Default for Config {
+    fn default() -> Config {
+        Config { field: 0, level: 9 }
+    }
+}
+
+impl Options for Config {
+    fn set_option(&mut self, name: &str, value: u32) -> Result<bool, String> {
+        match name {
+            "level" => self.level = game::check_range(name, value, 1, MAX_LEVEL)? - 1,
+            "field" => self.field = game::check_range(name, value, 1, MAX_FIELDS as u32)? - 1,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl Persist for Config {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u32::<LittleEndian>(self.field)?;
+        w.write_u32::<LittleEndian>(self.level)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Config> {
+        let field = r.read_u32::<LittleEndian>()?;
+        let level = r.read_u32::<LittleEndian>()?;
+        if field >= MAX_FIELDS as u32 || level >= MAX_LEVEL {
+            return Err(persist::invalid_data("snake settings out of range"));
+        }
+        Ok(Config { field, level })
+    }
+}
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Status {
@@ -29,8 +74,108 @@ pub enum Status {
     Menu(u32),
 }
 
+impl Persist for Status {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Status::Active => w.write_u8(0),
+            Status::Paused => w.write_u8(1),
+            Status::Raising(f) => {
+                w.write_u8(2)?;
+                f.save(w)
+            }
+            Status::Lowering(f) => {
+                w.write_u8(3)?;
+                f.save(w)
+            }
+            Status::Menu(f) => {
+                w.write_u8(4)?;
+                f.save(w)
+            }
+        }
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Status> {
+        Ok(match r.read_u8()? {
+            0 => Status::Active,
+            1 => Status::Paused,
+            2 => Status::Raising(usize::load(r)?),
+            3 => Status::Lowering(usize::load(r)?),
+            4 => Status::Menu(u32::load(r)?),
+            _ => return Err(persist::invalid_data("bad snake status")),
+        })
+    }
+}
+
 const BONUS_TIME : u32 = 24;
 const NO_BONUS_TIME : u32 = 32;
+const SAVE_VERSION: u32 = 3;
+// the shape of the score table in files from before it had a header.
+const LEGACY_SCORES: (usize, usize) = (20, 4);
+
+// lifetime statistics particular to snake.
+const STATS: &[(&str, Kind)] = &[
+    ("LONGEST", Kind::Best),
+    ("BONUSES", Kind::Count),
+];
+
+// Things worth doing in snake.
+fn achievements<'a>() -> [Achievement<Snake<'a>>; 2] {
+    [
+        // as long as half the open cells of the second field.
+        Achievement {
+            name: "HALF FULL",
+            earned: |g| g.config.field == 1 && g.length as usize * 2 >= open_cells(1),
+        },
+        Achievement {
+            name: "FIFTY LONG",
+            earned: |g| g.length >= 50,
+        },
+    ]
+}
+
+// the cells of a field that aren't wall, counted the first time they're
+// asked for.
+fn open_cells(field: usize) -> usize {
+    lazy_static! {
+        static ref OPEN: Vec<usize> = (0..MAX_FIELDS)
+            .map(|n| {
+                let f = fields::field(n);
+                let (w, h) = f.size();
+                (0..w).flat_map(|x| (0..h).map(move |y| (x, y))).filter(|&p| f[p].is_empty()).count()
+            })
+            .collect();
+    }
+    OPEN[field]
+}
+
+// How fast the snake crawls at each level: a steady climb from two cells a
+// second, ending a little past one cell a tick.
+const CRAWL: [u32; MAX_LEVEL as usize] = [
+    speed::every(10),
+    speed::every(9),
+    speed::every(8),
+    speed::every(7),
+    speed::every(6),
+    speed::every(5),
+    speed::CELL * 2 / 9,
+    speed::every(4),
+    speed::CELL * 2 / 7,
+    speed::every(3),
+    speed::CELL * 2 / 5,
+    speed::every(2),
+    speed::CELL * 3 / 5,
+    speed::CELL * 2 / 3,
+    speed::CELL * 3 / 4,
+    speed::CELL * 4 / 5,
+    speed::CELL * 9 / 10,
+    speed::CELL,
+    speed::CELL * 5 / 4,
+    speed::CELL * 3 / 2,
+];
+
+fn crawl(level: u32) -> u32 {
+    CRAWL[level as usize]
+}
 
 pub struct Snake<'a> {
     pub config: Config,
@@ -40,15 +185,22 @@ pub struct Snake<'a> {
     pub food_position: (usize,usize),
     pub bonus_position: Option<(usize, usize)>,
     pub anim_tick: u32,
-    movement_tick: u32,
+    movement: Motion,
     tail_position: (usize,usize),
     input: InputState,
     board: Imprint<CellData>,
     points: u32,
-    score_table: ScoreTable<'a>,
-    speed: u32,
+    scores: Leaderboard<'a>,
+    stats: Stats<'a>,
+    achievements: Achievements<'a>,
+    length: u32,
+    // what happened during the current tick.
+    events: Vec<Event>,
+    level: u32,
     growth: u32,
     pub bonus_timer: u32,
+    rng: Random,
+    saved: SavedGame<'a>,
 }
 
 
@@ -77,12 +229,54 @@ impl Direction {
         }
     }
 }
+
+impl Persist for Direction {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(match *self {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        })
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Direction> {
+        Ok(match r.read_u8()? {
+            0 => Direction::Up,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            3 => Direction::Right,
+            _ => return Err(persist::invalid_data("bad direction")),
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CellData {
     Snake(Direction),
     Wall
 }
 
+impl Persist for CellData {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            CellData::Snake(d) => {
+                w.write_u8(0)?;
+                d.save(w)
+            }
+            CellData::Wall => w.write_u8(1),
+        }
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<CellData> {
+        Ok(match r.read_u8()? {
+            0 => CellData::Snake(Direction::load(r)?),
+            1 => CellData::Wall,
+            _ => return Err(persist::invalid_data("bad snake cell")),
+        })
+    }
+}
+
 fn move_dir((x,y) : (usize,usize), d : Direction) -> (usize,usize) {
     let rx = match d {
         Direction::Up | Direction::Down => x,
@@ -98,34 +292,44 @@ fn move_dir((x,y) : (usize,usize), d : Direction) -> (usize,usize) {
 }
 impl<'a> Snake<'a> {
 
-    pub fn new(filename: &'a Path) -> ::std::io::Result<Self> {
+    pub fn new(storage: &'a dyn Storage, key: &'a str, seed: u64) -> Self {
         let mut g = Snake {
-            config: Config { field: 0, level: 9 },
+            config: Config::default(),
             status: Status::Menu(0),
             board: fields::field(0),
-            movement_tick: 0,
-            speed: MAX_LEVEL - 9,
+            movement: Motion::new(),
+            level: 9,
             direction: Direction::Right,
             head_position: (WIDTH/2, HEIGHT/2),
             tail_position: (WIDTH/2, HEIGHT/2),
             food_position: (0, 0),
             bonus_position: None,
             points: 0,
-            score_table: ScoreTable::new(filename)?,
+            scores: Leaderboard::new(storage, key, (MAX_LEVEL as usize, MAX_FIELDS), LEGACY_SCORES, "LENGTH", &[]),
+            stats: Stats::new(storage, key, STATS),
+            achievements: Achievements::new(storage, key),
+            length: 1,
+            events: Vec::new(),
             input: InputState::new(),
             growth: 3,
             bonus_timer: NO_BONUS_TIME,
             anim_tick:0,
+            rng: Random::new(seed),
+            saved: SavedGame::new(storage, key, SAVE_VERSION),
         };
         g.food_position = g.random_free_spot();
-        Ok(g)
+        g
     }
 
     fn new_game(&mut self) {
         self.board = fields::field(self.config.field as usize);
-        self.score_table
-            .update_scores(&self.config, self.points)
-            .unwrap();
+        let score = Score::new(self.points, self.level, self.length, 0);
+        let high = self.scores.finish(&mut self.input, self.config.level, self.config.field, score);
+        if high {
+            self.events.push(Event::HighScore(self.points));
+        }
+        self.stats.save();
+        self.length = 1;
         self.direction = Direction::Right;
         self.tail_position = (WIDTH/2,HEIGHT/2);
         self.head_position = (WIDTH/2,HEIGHT/2);
@@ -134,12 +338,85 @@ impl<'a> Snake<'a> {
         self.food_position = self.random_free_spot();
         self.bonus_timer = NO_BONUS_TIME;
         self.points = 0;
-        self.speed = MAX_LEVEL - self.config.level;
+        self.level = self.config.level;
+        self.movement.reset();
     }
 
-    fn random_free_spot(&self) -> (usize,usize){
-        let x = ::rand::random::<u32>() as usize % WIDTH;
-        let y = ::rand::random::<u32>() as usize % HEIGHT;
+    fn save_game(&self) -> io::Result<()> {
+        let mut file = self.saved.start()?;
+        self.config.save(&mut file)?;
+        match self.status {
+            Status::Active => Status::Paused,
+            ref s => s.clone(),
+        }.save(&mut file)?;
+        self.head_position.save(&mut file)?;
+        self.direction.save(&mut file)?;
+        self.food_position.save(&mut file)?;
+        self.bonus_position.save(&mut file)?;
+        self.anim_tick.save(&mut file)?;
+        self.movement.save(&mut file)?;
+        self.tail_position.save(&mut file)?;
+        self.board.save(&mut file)?;
+        self.points.save(&mut file)?;
+        self.length.save(&mut file)?;
+        self.level.save(&mut file)?;
+        self.growth.save(&mut file)?;
+        self.bonus_timer.save(&mut file)?;
+        self.rng.save(&mut file)?;
+        self.saved.write(&file)
+    }
+
+    fn resume_game(&mut self) -> io::Result<()> {
+        let bytes = self.saved.read()?;
+        let mut file = &bytes[..];
+        let config = Config::load(&mut file)?;
+        let status = Status::load(&mut file)?;
+        let head_position = <(usize, usize)>::load(&mut file)?;
+        let direction = Direction::load(&mut file)?;
+        let food_position = <(usize, usize)>::load(&mut file)?;
+        let bonus_position = Option::load(&mut file)?;
+        let anim_tick = u32::load(&mut file)?;
+        let movement = Motion::load(&mut file)?;
+        let tail_position = <(usize, usize)>::load(&mut file)?;
+        let board = Imprint::load(&mut file)?;
+        let points = u32::load(&mut file)?;
+        let length = u32::load(&mut file)?;
+        let level = u32::load(&mut file)?;
+        let growth = u32::load(&mut file)?;
+        let bonus_timer = u32::load(&mut file)?;
+        let rng = Random::load(&mut file)?;
+        let on_board = |(x, y): (usize, usize)| x < WIDTH && y < HEIGHT;
+        // only what suspend writes, with the animation within what tick counts.
+        let status_ok = status == Status::Paused && anim_tick < 4;
+        if board.size() != (WIDTH, HEIGHT) || level >= MAX_LEVEL || !status_ok ||
+            bonus_timer == 0 || !on_board(head_position) || !on_board(food_position) ||
+            !on_board(tail_position) || !bonus_position.is_none_or(on_board)
+        {
+            return Err(persist::invalid_data("saved snake game is inconsistent"));
+        }
+        self.saved.remove()?;
+        self.config = config;
+        self.status = status;
+        self.head_position = head_position;
+        self.direction = direction;
+        self.food_position = food_position;
+        self.bonus_position = bonus_position;
+        self.anim_tick = anim_tick;
+        self.movement = movement;
+        self.tail_position = tail_position;
+        self.board = board;
+        self.points = points;
+        self.length = length;
+        self.level = level;
+        self.growth = growth;
+        self.bonus_timer = bonus_timer;
+        self.rng = rng;
+        Ok(())
+    }
+
+    fn random_free_spot(&mut self) -> (usize,usize){
+        let x = self.rng.gen::<u32>() as usize % WIDTH;
+        let y = self.rng.gen::<u32>() as usize % HEIGHT;
         if !self.board[(x,y)].is_empty() || self.head_position == (x,y) || self.food_position == (x,y) {
             for xo in 0..WIDTH {
                 for yo in 0..HEIGHT {
@@ -150,7 +427,7 @@ impl<'a> Snake<'a> {
                 }
             }
         }
-        return (x,y)
+        (x,y)
     }
 
     fn advance(&mut self) {
@@ -170,12 +447,14 @@ impl<'a> Snake<'a> {
         self.board[self.head_position] = Cell::Filled(CellData::Snake(self.direction));
         let new_loc = move_dir(self.head_position, self.direction);
         if !self.board[new_loc].is_empty() {
+            self.events.push(Event::GameOver);
             self.status = Status::Raising(0);
             return;
         }
         if new_loc == self.food_position {
-            self.points += MAX_LEVEL - self.speed + 1;
+            self.points += self.level + 1;
             self.growth += 1;
+            self.events.push(Event::FoodEaten);
             self.food_position = self.random_free_spot();
         }
         self.head_position = new_loc;
@@ -184,9 +463,13 @@ impl<'a> Snake<'a> {
             self.points += (self.bonus_timer * 4) + 4;
             self.bonus_position = None;
             self.bonus_timer = NO_BONUS_TIME;
+            self.stats.add("BONUSES", 1);
+            self.events.push(Event::BonusEaten);
         }
         if self.growth > 0 {
             self.growth -= 1;
+            self.length += 1;
+            self.stats.add("LONGEST", self.length as u64);
         } else {
             if let Cell::Filled(CellData::Snake(d)) = self.board[self.tail_position] {
                 self.board[self.tail_position] = Cell::Empty;
@@ -205,29 +488,45 @@ impl<'a> Snake<'a> {
 }
 
 impl<'a> Game for Snake<'a> {
+    type Config = Config;
     type CellData = CellData;
     fn current_level(&self) -> u32 {
-        MAX_LEVEL - self.speed
+        self.level
     }
     fn score(&self) -> u32 {
         self.points
     }
     fn top_score(&self) -> u32 {
-        self.score_table.get_top_score(&self.config)
+        self.scores.get_top_score(self.config.level, self.config.field)
     }
     fn board(&self) -> &Imprint<CellData> {
         &self.board
     }
-    fn next(&self) -> Option<&Imprint<CellData>> {
-        None
+    fn next(&self) -> Vec<&Imprint<CellData>> {
+        Vec::new()
     }
     fn tick(&mut self) -> TickResult {
+        self.events.clear();
+        self.achievements.tick();
+        if self.scores.is_open() {
+            self.scores.tick(&mut self.input);
+            return TickResult::Continue;
+        }
+        if self.stats.is_open() {
+            self.stats.tick(&mut self.input);
+            return TickResult::Continue;
+        }
+        if matches!(self.status, Status::Active) {
+            self.stats.add(stats::TIME, 1);
+        }
         match self.status {
             Status::Active => {
                 if self.input.escape {
                     self.status = Status::Paused;
                     self.input.escape = false;
                 } else {
+                    // the snake heads whichever way is held, so there is
+                    // nothing for it to repeat.
                     if self.input.left {
                         self.set_direction(Direction::Left);
                     }
@@ -248,10 +547,12 @@ impl<'a> Game for Snake<'a> {
                         let d = self.direction.turn_right();
                         self.set_direction(d);
                     }
-                    if self.movement_tick == 0 {
+                    for _ in 0..self.movement.advance(crawl(self.level)) {
+                        if self.status != Status::Active {
+                            break;
+                        }
                         self.advance();
                     }
-                    self.movement_tick = (self.movement_tick + 1) % self.speed;
                     self.anim_tick = (self.anim_tick + 1) % 4;
                 }
             }
@@ -262,6 +563,7 @@ impl<'a> Game for Snake<'a> {
                     self.status = Status::Active;
                 } else if self.input.escape {
                     self.input.escape = false;
+                    self.events.push(Event::GameOver);
                     self.status = Status::Raising(0);
                 }
             }
@@ -295,13 +597,12 @@ impl<'a> Game for Snake<'a> {
                     self.input.escape = false;
                     return TickResult::PrevGame;
                 }
-                if self.input.right {
-                    self.input.right = false;
+                // a step a tick, however fast the directions repeat.
+                if self.input.shift > 0 {
                     self.config.field = (self.config.field + 1) % fields::MAX_FIELDS as u32;
                     self.new_game();
                 }
-                if self.input.left {
-                    self.input.left= false;
+                if self.input.shift < 0 {
                     if self.config.field == 0 {
                         self.config.field = fields::MAX_FIELDS as u32 - 1;
                     } else {
@@ -312,30 +613,83 @@ impl<'a> Game for Snake<'a> {
                 if self.input.drop {
                     self.input.drop = false;
                     self.status = Status::Active;
+                    self.stats.add(stats::GAMES, 1);
                 }
-                if self.input.up {
-                    self.input.up = false;
-                    if self.config.level < MAX_LEVEL - 1 {
-                        self.config.level += 1;
-                        self.speed = MAX_LEVEL - self.config.level;
-                    }
+                if self.input.stats {
+                    self.input.stats = false;
+                    self.stats.show();
                 }
-                if self.input.down {
-                    self.input.down = false;
-                    if self.config.level > 0 {
-                        self.config.level -= 1;
-                        self.speed = MAX_LEVEL - self.config.level;
+                if self.input.button_b {
+                    self.input.button_b = false;
+                    self.scores.browse(self.config.level, self.config.field);
+                }
+                if self.input.button_a {
+                    self.input.button_a = false;
+                    if self.saved.exists() {
+                        if let Err(e) = self.resume_game() {
+                            eprintln!("could not resume the saved game: {}", e);
+                        }
                     }
                 }
+                if self.input.climb < 0 && self.config.level < MAX_LEVEL - 1 {
+                    self.config.level += 1;
+                    self.level = self.config.level;
+                }
+                if self.input.climb > 0 && self.config.level > 0 {
+                    self.config.level -= 1;
+                    self.level = self.config.level;
+                }
             }
         }
+        let earned = self.achievements.newly_earned(self, &achievements());
+        self.achievements.earn(&earned);
         TickResult::Continue
     }
     fn is_paused(&self) -> bool {
-        return self.status == Status::Paused;
+        self.status == Status::Paused
     }
 
     fn input_state(&mut self) -> &mut InputState {
         &mut self.input
     }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+        self.new_game();
+    }
+
+    fn suspend(&mut self) -> io::Result<()> {
+        self.stats.save();
+        match self.status {
+            Status::Active | Status::Paused => self.save_game(),
+            _ => Ok(()),
+        }
+    }
+
+    fn can_resume(&self) -> bool {
+        match self.status {
+            Status::Menu(_) => self.saved.exists(),
+            _ => false,
+        }
+    }
+
+    fn score_table(&self) -> &Leaderboard<'_> {
+        &self.scores
+    }
+
+    fn statistics(&self) -> &Stats<'_> {
+        &self.stats
+    }
+
+    fn achievements(&self) -> &Achievements<'_> {
+        &self.achievements
+    }
+
+    fn events(&self) -> &[Event] {
+        &self.events
+    }
 }
@@ -0,0 +1,52 @@
+use std::io::{self, Read, Write};
+
+use persist::{self, Persist};
+
+// Speeds are measured in fractions of a cell per tick: CELL is one cell
+// every tick, CELL / 2 one every other tick, CELL * 3 three cells a tick.
+pub const CELL: u32 = 0x1_0000;
+
+// The speed that covers one cell every `ticks` ticks. Rounded down, so that
+// it is never faster than asked, though a cell may take a tick longer.
+pub const fn every(ticks: u32) -> u32 {
+    CELL / ticks
+}
+
+// Progress through the current cell of something moving at a given speed,
+// such as a falling piece.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Motion {
+    progress: u32,
+}
+
+impl Motion {
+    pub fn new() -> Motion {
+        Motion { progress: 0 }
+    }
+
+    // moves on by one tick, returning how many whole cells were covered.
+    pub fn advance(&mut self, speed: u32) -> u32 {
+        self.progress += speed;
+        let cells = self.progress / CELL;
+        self.progress %= CELL;
+        cells
+    }
+
+    pub fn reset(&mut self) {
+        self.progress = 0;
+    }
+}
+
+impl Persist for Motion {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.progress.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Motion> {
+        let progress = u32::load(r)?;
+        if progress >= CELL {
+            return Err(persist::invalid_data("motion out of range"));
+        }
+        Ok(Motion { progress })
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use game::InputState;
+use persist;
+use storage::{self, Storage};
+
+const MAGIC: &[u8; 4] = b"BGST";
+const VERSION: u32 = 1;
+
+// every game keeps these two.
+pub const GAMES: &str = "GAMES";
+pub const TIME: &str = "TIME";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Kind {
+    // a running total.
+    Count,
+    // a running total of ticks played, shown as hours and minutes.
+    Time,
+    // the most there has ever been at once.
+    Best,
+}
+
+// Figures kept across every game ever played, such as how many have been
+// played and for how long, along with the screen that shows them. Each game
+// chooses the rest for itself, named by the labels the screen shows them
+// under. They are stored by name too, so a game can gain or lose some
+// without the others being reset.
+pub struct Stats<'a> {
+    entries: Vec<(&'static str, Kind, u64)>,
+    storage: &'a dyn Storage,
+    key: String,
+    // whether anything has changed since they were last stored.
+    changed: bool,
+    open: bool,
+}
+
+impl<'a> Stats<'a> {
+    pub fn new(storage: &'a dyn Storage, key: &str, kinds: &[(&'static str, Kind)]) -> Stats<'a> {
+        let mut entries = vec![(GAMES, Kind::Count, 0), (TIME, Kind::Time, 0)];
+        entries.extend(kinds.iter().map(|&(name, kind)| (name, kind, 0)));
+        let mut it = Stats {
+            entries,
+            storage,
+            key: format!("{}.stats", key),
+            changed: false,
+            open: false,
+        };
+        match storage::read_with_backup(storage, &it.key, |bytes| read_stats(&mut &bytes[..])) {
+            Ok(Some(stored)) => {
+                for (name, value) in stored {
+                    if let Some(e) = it.entries.iter_mut().find(|e| e.0 == name) {
+                        e.2 = value;
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("{}: {}", it.key, e),
+        }
+        it
+    }
+
+    pub fn entries(&self) -> &[(&'static str, Kind, u64)] {
+        &self.entries
+    }
+
+    // adds to a total, or raises a best if `value` beats it.
+    pub fn add(&mut self, name: &str, value: u64) {
+        if let Some(e) = self.entries.iter_mut().find(|e| e.0 == name) {
+            let new = match e.1 {
+                Kind::Count | Kind::Time => e.2 + value,
+                Kind::Best => e.2.max(value),
+            };
+            if new != e.2 {
+                e.2 = new;
+                self.changed = true;
+            }
+        }
+    }
+
+    // stores them if anything has changed. Failing that, they are kept in
+    // memory to try again later.
+    pub fn save(&mut self) {
+        if !self.changed {
+            return;
+        }
+        let mut w = Vec::new();
+        let written = write_stats(&mut w, &self.entries)
+            .and_then(|_| storage::write_with_backup(self.storage, &self.key, &w));
+        match written {
+            Ok(()) => self.changed = false,
+            Err(e) => eprintln!("{}: {}", self.key, e),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn show(&mut self) {
+        self.open = true;
+    }
+
+    pub fn tick(&mut self, input: &mut InputState) {
+        if input.escape || input.drop || input.button_a || input.button_b || input.stats {
+            input.escape = false;
+            input.drop = false;
+            input.button_a = false;
+            input.button_b = false;
+            input.stats = false;
+            self.open = false;
+        }
+    }
+}
+
+fn write_stats<W: Write>(w: &mut W, entries: &[(&'static str, Kind, u64)]) -> io::Result<()> {
+    persist::write_header(w, MAGIC, VERSION)?;
+    w.write_u32::<LittleEndian>(entries.len() as u32)?;
+    for &(name, _, value) in entries {
+        persist::write_string(w, name)?;
+        w.write_u64::<LittleEndian>(value)?;
+    }
+    Ok(())
+}
+
+fn read_stats<R: Read>(r: &mut R) -> io::Result<Vec<(String, u64)>> {
+    if persist::read_header(r, MAGIC)? != VERSION {
+        return Err(persist::invalid_data("unsupported stats version"));
+    }
+    let n = persist::read_len(r)?;
+    let mut stats = Vec::new();
+    for _ in 0..n {
+        let name = persist::read_string(r)?;
+        stats.push((name, r.read_u64::<LittleEndian>()?));
+    }
+    Ok(stats)
+}
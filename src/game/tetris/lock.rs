@@ -0,0 +1,208 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use persist::{self, Persist};
+use TICK_RATE;
+
+// How long a piece may rest on something before it locks, and what buys it
+// more time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LockReset {
+    // as the Nintendo games do: no waiting, the piece locks the next time
+    // it is due to fall and can't.
+    Classic,
+    // the wait starts over only when the piece falls lower than it has been.
+    Step,
+    // as the guideline games do: moving or turning the piece starts the
+    // wait over too, but only so many times before it locks as soon as it
+    // lands.
+    Move,
+    // moving or turning it starts the wait over, as often as it likes.
+    Infinite,
+}
+
+pub const LOCK_RESETS: [LockReset; 4] =
+    [LockReset::Classic, LockReset::Step, LockReset::Move, LockReset::Infinite];
+
+// how long a piece rests before it locks, where it can.
+pub const LOCK_DELAY: u32 = TICK_RATE / 2;
+// how many moves and turns start the wait over under move reset.
+pub const MOVE_RESETS: u32 = 15;
+
+impl LockReset {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LockReset::Classic => "classic",
+            LockReset::Step => "step",
+            LockReset::Move => "move",
+            LockReset::Infinite => "infinite",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LockReset> {
+        LOCK_RESETS.iter().cloned().find(|r| r.name() == name)
+    }
+
+    // whether a piece that lands, by falling or being pushed down, locks
+    // there and then rather than waiting.
+    pub fn locks_at_once(&self) -> bool {
+        *self == LockReset::Classic
+    }
+}
+
+impl Persist for LockReset {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(*self as u8)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<LockReset> {
+        match LOCK_RESETS.get(r.read_u8()? as usize) {
+            Some(&reset) => Ok(reset),
+            None => Err(persist::invalid_data("bad lock reset")),
+        }
+    }
+}
+
+// How the piece in play is getting on towards locking.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lock {
+    // ticks spent resting since the wait last started over.
+    ticks: u32,
+    // moves and turns that have started it over.
+    moves: u32,
+    // the lowest row the piece has reached.
+    lowest: i32,
+}
+
+impl Lock {
+    pub fn new(y: i32) -> Lock {
+        Lock { ticks: 0, moves: 0, lowest: y }
+    }
+
+    // the piece has fallen to row `y`.
+    pub fn fell(&mut self, y: i32) {
+        if y > self.lowest {
+            self.lowest = y;
+            self.ticks = 0;
+            self.moves = 0;
+        }
+    }
+
+    // the piece has moved or turned.
+    pub fn moved(&mut self, reset: LockReset) {
+        match reset {
+            LockReset::Move if self.moves < MOVE_RESETS => {
+                self.moves += 1;
+                self.ticks = 0;
+            }
+            LockReset::Infinite => self.ticks = 0,
+            _ => {}
+        }
+    }
+
+    // a tick spent resting on something, returning whether that's the end
+    // of the wait. Classic pieces don't wait, and are locked by falling.
+    pub fn rest(&mut self, reset: LockReset) -> bool {
+        match reset {
+            LockReset::Classic => false,
+            LockReset::Move if self.moves >= MOVE_RESETS => true,
+            _ => {
+                self.ticks += 1;
+                self.ticks >= LOCK_DELAY
+            }
+        }
+    }
+}
+
+impl Persist for Lock {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.ticks.save(w)?;
+        self.moves.save(w)?;
+        self.lowest.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Lock> {
+        Ok(Lock {
+            ticks: u32::load(r)?,
+            moves: u32::load(r)?,
+            lowest: i32::load(r)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // rests `ticks` times, returning the tick of them that locked, if any.
+    fn rest_for(lock: &mut Lock, reset: LockReset, ticks: u32) -> Option<u32> {
+        (1..=ticks).find(|_| lock.rest(reset))
+    }
+
+    #[test]
+    fn classic_locks_on_landing_and_never_waits() {
+        assert!(LockReset::Classic.locks_at_once());
+        let mut lock = Lock::new(0);
+        assert_eq!(rest_for(&mut lock, LockReset::Classic, LOCK_DELAY * 4), None);
+    }
+
+    #[test]
+    fn others_wait_on_landing_even_pushed_down() {
+        for &reset in &[LockReset::Step, LockReset::Move, LockReset::Infinite] {
+            assert!(!reset.locks_at_once());
+            let mut lock = Lock::new(0);
+            assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY * 2), Some(LOCK_DELAY));
+        }
+    }
+
+    #[test]
+    fn step_starts_over_only_on_reaching_a_new_row() {
+        let reset = LockReset::Step;
+        let mut lock = Lock::new(0);
+        assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY - 1), None);
+        lock.moved(reset);
+        lock.fell(0);
+        assert_eq!(rest_for(&mut lock, reset, 1), Some(1));
+
+        let mut lock = Lock::new(0);
+        assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY - 1), None);
+        lock.fell(1);
+        assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY), Some(LOCK_DELAY));
+    }
+
+    #[test]
+    fn move_starts_over_up_to_the_cap() {
+        let reset = LockReset::Move;
+        let mut lock = Lock::new(0);
+        for _ in 0..MOVE_RESETS {
+            assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY - 1), None);
+            lock.moved(reset);
+        }
+        // out of moves, it locks as soon as it rests.
+        assert_eq!(rest_for(&mut lock, reset, 1), Some(1));
+    }
+
+    #[test]
+    fn move_cap_starts_over_on_reaching_a_new_row() {
+        let reset = LockReset::Move;
+        let mut lock = Lock::new(0);
+        for _ in 0..MOVE_RESETS {
+            lock.moved(reset);
+        }
+        lock.fell(2);
+        lock.moved(reset);
+        assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY), Some(LOCK_DELAY));
+    }
+
+    #[test]
+    fn infinite_starts_over_as_often_as_it_likes() {
+        let reset = LockReset::Infinite;
+        let mut lock = Lock::new(0);
+        for _ in 0..MOVE_RESETS * 10 {
+            assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY - 1), None);
+            lock.moved(reset);
+        }
+        assert_eq!(rest_for(&mut lock, reset, LOCK_DELAY), Some(LOCK_DELAY));
+    }
+}
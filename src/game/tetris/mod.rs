@@ -1,26 +1,197 @@
-use std::path::Path;
+use std::io::{self, Read, Write};
 
 
-mod score_table;
+pub mod lock;
 mod piece;
+pub mod randomizer;
+pub mod rotation;
 
-use self::score_table::ScoreTable;
-use self::piece::Piece;
+use self::lock::{Lock, LockReset, LOCK_RESETS};
+pub use self::piece::Piece;
+use self::randomizer::{Dealer, Randomizer, LABELS, RANDOMIZERS};
+use self::rotation::{Rotation, ROTATIONS};
 
-use game::{Game, InputState, TickResult};
+use game::{self, Event, Game, InputState, Options, TickResult};
+use game::achievements::{Achievement, Achievements};
+use game::leaderboard::Leaderboard;
+use game::score_table::Score;
+use game::stats::{self, Kind, Stats};
+use game::speed::{self, Motion};
+use game::saved::SavedGame;
+use persist::{self, Persist};
 use imprint::{Imprint, Cell};
+use random::Random;
+use storage::Storage;
+use TICK_RATE;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 pub const WIDTH: usize = 10;
 pub const HEIGHT: usize = 20;
 pub const BUFFER: usize = 2;
 pub const ADVANCE_SPEED: i32 = 11;
-pub const MAX_LEVEL: u32 = ::FRAMERATE; // should always be <= FRAMERATE
+pub const MAX_LEVEL: u32 = 20;
 pub const MAX_BTYPE: u32 = 14;
-pub const KEY_DELAY: u32 = 2;
+// the most upcoming pieces that can be shown.
+pub const MAX_PREVIEWS: u32 = 6;
+// the longest wait for the next piece after one locks.
+pub const MAX_ARE: u32 = TICK_RATE;
+const SAVE_VERSION: u32 = 9;
+// the shape of the score table in files from before it had a header.
+const LEGACY_SCORES: (usize, usize) = (20, 15);
+
+// lifetime statistics particular to tetris.
+const STATS: &[(&str, Kind)] = &[
+    ("LINES", Kind::Count),
+    ("TETRISES", Kind::Count),
+    ("I PIECES", Kind::Count),
+    ("O PIECES", Kind::Count),
+    ("J PIECES", Kind::Count),
+    ("L PIECES", Kind::Count),
+    ("S PIECES", Kind::Count),
+    ("T PIECES", Kind::Count),
+    ("Z PIECES", Kind::Count),
+];
+
+// Things worth doing in tetris.
+fn achievements<'a>() -> [Achievement<Tetris<'a>>; 3] {
+    [
+        Achievement {
+            name: "FOUR AT ONCE",
+            earned: |g| matches!(g.status, Status::Clearing(_)) && g.lines.len() == 4,
+        },
+        // level 15, starting at the greatest height.
+        Achievement {
+            name: "HIGH RISE",
+            earned: |g| g.config.btype == MAX_BTYPE && g.level >= 14,
+        },
+        Achievement {
+            name: "CENTURY",
+            earned: |g| g.cleared >= 100,
+        },
+    ]
+}
 
+// How fast pieces fall at each level: from one cell a second, speeding up
+// ever more steeply, to the last few levels where a piece falls several
+// cells a tick and the last where it drops to the bottom at once.
+const GRAVITY: [u32; MAX_LEVEL as usize] = [
+    speed::every(20),
+    speed::every(16),
+    speed::every(13),
+    speed::every(11),
+    speed::every(9),
+    speed::every(7),
+    speed::every(6),
+    speed::every(5),
+    speed::every(4),
+    speed::every(3),
+    speed::CELL * 2 / 5,
+    speed::every(2),
+    speed::CELL * 2 / 3,
+    speed::CELL,
+    speed::CELL * 3 / 2,
+    speed::CELL * 2,
+    speed::CELL * 3,
+    speed::CELL * 5,
+    speed::CELL * 10,
+    speed::CELL * (HEIGHT + BUFFER) as u32,
+];
+
+fn gravity(level: u32) -> u32 {
+    GRAVITY[level as usize]
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Config {
     pub btype: u32,
     pub level: u32,
+    pub rotation: Rotation,
+    // whether a piece can be put aside to bring in later.
+    pub hold: bool,
+    // how many upcoming pieces are shown.
+    pub previews: u32,
+    pub randomizer: Randomizer,
+    pub lock: LockReset,
+    // ticks to wait for the next piece after one locks without clearing
+    // any lines.
+    pub are: u32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            btype: 0,
+            level: 0,
+            rotation: Rotation::Nintendo,
+            hold: false,
+            previews: 1,
+            randomizer: Randomizer::Random,
+            lock: LockReset::Classic,
+            are: 0,
+        }
+    }
+}
+
+impl Options for Config {
+    fn set_option(&mut self, name: &str, value: u32) -> Result<bool, String> {
+        match name {
+            "level" => self.level = game::check_range(name, value, 1, MAX_LEVEL)? - 1,
+            "height" => self.btype = game::check_range(name, value, 0, MAX_BTYPE)?,
+            "rotation" => {
+                let i = game::check_range(name, value, 0, ROTATIONS.len() as u32 - 1)?;
+                self.rotation = ROTATIONS[i as usize];
+            }
+            "hold" => self.hold = game::check_range(name, value, 0, 1)? == 1,
+            "lock" => {
+                let i = game::check_range(name, value, 0, LOCK_RESETS.len() as u32 - 1)?;
+                self.lock = LOCK_RESETS[i as usize];
+            }
+            "are" => self.are = game::check_range(name, value, 0, MAX_ARE)?,
+            "previews" => self.previews = game::check_range(name, value, 1, MAX_PREVIEWS)?,
+            "randomizer" => {
+                let i = game::check_range(name, value, 0, RANDOMIZERS.len() as u32 - 1)?;
+                self.randomizer = RANDOMIZERS[i as usize];
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl Persist for Config {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u32::<LittleEndian>(self.btype)?;
+        w.write_u32::<LittleEndian>(self.level)?;
+        self.rotation.save(w)?;
+        self.hold.save(w)?;
+        self.previews.save(w)?;
+        self.randomizer.save(w)?;
+        self.lock.save(w)?;
+        self.are.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Config> {
+        let btype = r.read_u32::<LittleEndian>()?;
+        let level = r.read_u32::<LittleEndian>()?;
+        if btype > MAX_BTYPE || level >= MAX_LEVEL {
+            return Err(persist::invalid_data("tetris settings out of range"));
+        }
+        // replays from before these were settings end early.
+        let rotation = persist::load_or(r, Rotation::Nintendo)?;
+        let hold = persist::load_or(r, false)?;
+        let previews = persist::load_or(r, 1)?;
+        if !(1..=MAX_PREVIEWS).contains(&previews) {
+            return Err(persist::invalid_data("tetris settings out of range"));
+        }
+        let randomizer = persist::load_or(r, Randomizer::Random)?;
+        let lock = persist::load_or(r, LockReset::Classic)?;
+        let are = persist::load_or(r, 0)?;
+        if are > MAX_ARE {
+            return Err(persist::invalid_data("tetris settings out of range"));
+        }
+        Ok(Config { btype, level, rotation, hold, previews, randomizer, lock, are })
+    }
 }
 
 
@@ -33,7 +204,56 @@ pub enum Status {
     Lowering(usize),
     Menu(u32),
     Clearing(i32),
-    Placing(Piece, i32, i32),
+    // the piece just locked, where it was, and how long until the next.
+    Placing(Piece, i32, i32, u32),
+}
+
+impl Persist for Status {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Status::Active => w.write_u8(0),
+            Status::Paused => w.write_u8(1),
+            Status::Raising(f) => {
+                w.write_u8(2)?;
+                f.save(w)
+            }
+            Status::Lowering(f) => {
+                w.write_u8(3)?;
+                f.save(w)
+            }
+            Status::Menu(f) => {
+                w.write_u8(4)?;
+                f.save(w)
+            }
+            Status::Clearing(f) => {
+                w.write_u8(5)?;
+                f.save(w)
+            }
+            Status::Placing(p, x, y, f) => {
+                w.write_u8(6)?;
+                p.save(w)?;
+                (x, y).save(w)?;
+                f.save(w)
+            }
+        }
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Status> {
+        Ok(match r.read_u8()? {
+            0 => Status::Active,
+            1 => Status::Paused,
+            2 => Status::Raising(usize::load(r)?),
+            3 => Status::Lowering(usize::load(r)?),
+            4 => Status::Menu(u32::load(r)?),
+            5 => Status::Clearing(i32::load(r)?),
+            6 => {
+                let p = Piece::load(r)?;
+                let (x, y) = <(i32, i32)>::load(r)?;
+                Status::Placing(p, x, y, u32::load(r)?)
+            }
+            _ => return Err(persist::invalid_data("bad tetris status")),
+        })
+    }
 }
 
 pub struct Tetris<'a> {
@@ -41,73 +261,201 @@ pub struct Tetris<'a> {
     pub status: Status,
     pub current: Piece,
     pub position: (i32, i32),
+    lock: Lock,
     pub lines: Vec<usize>,
-    next: Piece,
+    // the pieces to come, soonest first, one for each preview.
+    next: Vec<Piece>,
+    dealer: Dealer,
+    // the piece put aside, when the hold is on.
+    pub held: Option<Piece>,
+    // whether the piece in play has been swapped with the held one already,
+    // which it can only be once.
+    hold_used: bool,
     input: InputState,
     board: Imprint<()>,
     points: u32,
-    score_table: ScoreTable<'a>,
+    scores: Leaderboard<'a>,
+    stats: Stats<'a>,
+    achievements: Achievements<'a>,
+    cleared: u32,
+    // what happened during the current tick.
+    events: Vec<Event>,
     drop_rate: u32,
-    gravity_tick: u32,
-    speed: u32,
+    gravity: Motion,
+    level: u32,
     remaining: i32,
+    rng: Random,
+    saved: SavedGame<'a>,
 }
 
 
 impl<'a> Tetris<'a> {
-    pub fn new(filename: &'a Path) -> ::std::io::Result<Self> {
+    pub fn new(storage: &'a dyn Storage, key: &'a str, seed: u64) -> Self {
         let mut g = Tetris {
-            config: Config { btype: 0, level: 0 },
+            config: Config::default(),
             status: Status::Menu(0),
             board: Imprint::empty(WIDTH, HEIGHT + BUFFER),
-            gravity_tick: 0,
-            speed: MAX_LEVEL,
+            gravity: Motion::new(),
+            level: 0,
             remaining: ADVANCE_SPEED,
             drop_rate: 0,
             current: Piece::I2,
-            next: Piece::I2,
+            next: Vec::new(),
+            dealer: Dealer::new(Randomizer::Random),
+            held: None,
+            hold_used: false,
             position: (0, 0),
+            lock: Lock::new(0),
             points: 0,
-            score_table: ScoreTable::new(filename)?,
+            scores: Leaderboard::new(storage, key, (MAX_LEVEL as usize, MAX_BTYPE as usize + 1), LEGACY_SCORES, "LINES", &LABELS),
+            stats: Stats::new(storage, key, STATS),
+            achievements: Achievements::new(storage, key),
+            cleared: 0,
+            events: Vec::new(),
             input: InputState::new(),
             lines: Vec::new(),
+            rng: Random::new(seed),
+            saved: SavedGame::new(storage, key, SAVE_VERSION),
         };
         g.new_piece();
-        g.new_piece();
-        Ok(g)
+        g
     }
 
     fn new_piece(&mut self) {
-        self.current = self.next;
-        self.gravity_tick = 0;
-        self.next = ::rand::random::<Piece>();
+        // one for now, and one for each preview after.
+        while self.next.len() <= self.config.previews as usize {
+            let p = self.dealer.deal(&mut self.rng);
+            let p = self.config.rotation.spawn(p);
+            self.next.push(p);
+        }
+        let p = self.next.remove(0);
+        self.hold_used = false;
+        self.spawn(p);
+    }
+
+    // bring a piece in at the top.
+    fn spawn(&mut self, p: Piece) {
+        self.current = p;
+        self.gravity.reset();
         let x = (WIDTH as i32 - self.current.imprint().size().0 as i32) / 2;
-        let y = if self.current == Piece::I1 { 0 } else { 1 };
+        // every piece comes in with its top row just below the buffer.
+        let y = BUFFER as i32 - self.current.imprint().first_row() as i32;
         self.position = (x, y);
+        self.lock = Lock::new(y);
         if !self.move_piece(x, y) || !self.board.all_clear(BUFFER) {
+            self.events.push(Event::GameOver);
             self.status = Status::Raising(self.board.size().1);
         }
     }
 
     fn new_game(&mut self) {
         self.board = Imprint::empty(WIDTH, HEIGHT + BUFFER);
-        self.score_table
-            .update_scores(&self.config, self.points)
-            .unwrap();
-        self.new_piece();
+        let score = Score::new(self.points, self.level, self.cleared, self.config.randomizer as u32);
+        let high = self.scores.finish(&mut self.input, self.config.level, self.config.btype, score);
+        if high {
+            self.events.push(Event::HighScore(self.points));
+        }
+        self.stats.save();
+        self.cleared = 0;
+        self.held = None;
+        self.next.clear();
+        self.dealer = Dealer::new(self.config.randomizer);
         self.new_piece();
         self.points = 0;
         self.drop_rate = 0;
-        self.speed = MAX_LEVEL - self.config.level;
+        self.level = self.config.level;
         self.remaining = (self.config.level + 1) as i32 * ADVANCE_SPEED;
         for i in 0..self.config.btype {
             let top = self.board.size().1 - 1 - i as usize;
-            self.board.random_line(top, Cell::Filled(()));
+            self.board.random_line(top, Cell::Filled(()), &mut self.rng);
         }
     }
 
+    fn save_game(&self) -> io::Result<()> {
+        let mut file = self.saved.start()?;
+        self.config.save(&mut file)?;
+        match self.status {
+            Status::Active => Status::Paused,
+            ref s => s.clone(),
+        }.save(&mut file)?;
+        self.current.save(&mut file)?;
+        self.position.save(&mut file)?;
+        self.lock.save(&mut file)?;
+        self.lines.save(&mut file)?;
+        self.next.save(&mut file)?;
+        self.dealer.save(&mut file)?;
+        self.held.save(&mut file)?;
+        self.hold_used.save(&mut file)?;
+        self.board.save(&mut file)?;
+        self.points.save(&mut file)?;
+        self.cleared.save(&mut file)?;
+        self.drop_rate.save(&mut file)?;
+        self.gravity.save(&mut file)?;
+        self.level.save(&mut file)?;
+        self.remaining.save(&mut file)?;
+        self.rng.save(&mut file)?;
+        self.saved.write(&file)
+    }
+
+    fn resume_game(&mut self) -> io::Result<()> {
+        let bytes = self.saved.read()?;
+        let mut file = &bytes[..];
+        let config = Config::load(&mut file)?;
+        let status = Status::load(&mut file)?;
+        let current = Piece::load(&mut file)?;
+        let position = <(i32, i32)>::load(&mut file)?;
+        let lock = Lock::load(&mut file)?;
+        let lines = Vec::load(&mut file)?;
+        let next = Vec::load(&mut file)?;
+        let dealer = Dealer::load(&mut file)?;
+        let held = Option::load(&mut file)?;
+        let hold_used = bool::load(&mut file)?;
+        let board = Imprint::load(&mut file)?;
+        let points = u32::load(&mut file)?;
+        let cleared = u32::load(&mut file)?;
+        let drop_rate = u32::load(&mut file)?;
+        let gravity = Motion::load(&mut file)?;
+        let level = u32::load(&mut file)?;
+        let remaining = i32::load(&mut file)?;
+        let rng = Random::load(&mut file)?;
+        // only what suspend writes, with a piece still in play fitting where it
+        // was left and the counters within what tick counts down from.
+        let status_ok = match status {
+            Status::Paused => board.accepts(current.imprint(), position),
+            Status::Clearing(f) => !lines.is_empty() && (0..=lines.len() as i32 * 3).contains(&f),
+            Status::Placing(_, _, _, f) => f <= config.are,
+            _ => false,
+        };
+        if board.size() != (WIDTH, HEIGHT + BUFFER) || level >= MAX_LEVEL ||
+            next.len() != config.previews as usize || !status_ok ||
+            lines.iter().any(|&y| y >= HEIGHT + BUFFER)
+        {
+            return Err(persist::invalid_data("saved tetris game is inconsistent"));
+        }
+        self.saved.remove()?;
+        self.config = config;
+        self.status = status;
+        self.current = current;
+        self.position = position;
+        self.lock = lock;
+        self.lines = lines;
+        self.next = next;
+        self.dealer = dealer;
+        self.held = held;
+        self.hold_used = hold_used;
+        self.board = board;
+        self.points = points;
+        self.cleared = cleared;
+        self.drop_rate = drop_rate;
+        self.gravity = gravity;
+        self.level = level;
+        self.remaining = remaining;
+        self.rng = rng;
+        Ok(())
+    }
+
     fn award_points(&mut self, lines: u32) {
-        let level = (MAX_LEVEL - self.speed) + 1;
+        let level = self.level + 1;
         let award = match lines {
             1 => 40 * level,
             2 => 100 * level,
@@ -117,13 +465,20 @@ impl<'a> Tetris<'a> {
         self.points += award
     }
 
-    fn switch_piece(&mut self, p: Piece) -> bool {
-        if self.board.accepts(p.imprint(), self.position) {
-            self.current = p;
-            true
-        } else {
-            false
+    // turn the current piece to `p` at the first of the places the
+    // rotation system offers that it fits.
+    fn switch_piece(&mut self, p: Piece, kicks: &[(i32, i32)]) -> bool {
+        let (x, y) = self.position;
+        for &(dx, dy) in kicks {
+            // the kicks count y up, and the board counts it down.
+            let c = (x + dx, y - dy);
+            if self.board.accepts(p.imprint(), c) {
+                self.current = p;
+                self.position = c;
+                return true;
+            }
         }
+        false
     }
 
     fn move_piece(&mut self, x: i32, y: i32) -> bool {
@@ -136,21 +491,49 @@ impl<'a> Tetris<'a> {
         }
     }
 
+    // a move or turn that worked, which may give a resting piece more time.
+    fn moved(&mut self) {
+        self.lock.fell(self.position.1);
+        self.lock.moved(self.config.lock);
+    }
+
+    // whether the piece is resting on something.
+    fn grounded(&self) -> bool {
+        let (x, y) = self.position;
+        !self.board.accepts(self.current.imprint(), (x, y + 1))
+    }
+
+    // where a hard drop would leave the piece.
+    pub fn landing(&self) -> (i32, i32) {
+        let (x, mut y) = self.position;
+        while self.board.accepts(self.current.imprint(), (x, y + 1)) {
+            y += 1;
+        }
+        (x, y)
+    }
+
     fn hard_drop(&mut self) {
-        while self.status == Status::Active {
+        loop {
             self.drop_rate += 1;
-            self.down();
+            if !self.down() {
+                break;
+            }
         }
+        self.lock_piece();
     }
 
     fn rotate_l(&mut self) {
-        let p = self.current.rotate_l();
-        self.switch_piece(p);
+        let (p, kicks) = self.config.rotation.turn_l(self.current);
+        if self.switch_piece(p, kicks) {
+            self.moved();
+        }
     }
 
     fn rotate_r(&mut self) {
-        let p = self.current.rotate_r();
-        self.switch_piece(p);
+        let (p, kicks) = self.config.rotation.turn_r(self.current);
+        if self.switch_piece(p, kicks) {
+            self.moved();
+        }
     }
 
     fn check_lines(&mut self) -> bool {
@@ -160,82 +543,124 @@ impl<'a> Tetris<'a> {
     fn clear_lines(&mut self) {
         let lines = self.lines.len() as u32;
         self.award_points(lines);
+        self.cleared += lines;
+        self.events.push(Event::LinesCleared(lines));
+        self.stats.add("LINES", lines as u64);
+        if lines == 4 {
+            self.stats.add("TETRISES", 1);
+        }
         self.board.clear_lines(&mut self.lines)
     }
 
-    fn down(&mut self) {
+    // moves the piece down a row, returning false if there was no room.
+    fn down(&mut self) -> bool {
         let (x, y) = self.position;
-        if !self.move_piece(x, y + 1) {
-            self.points += self.drop_rate;
-            self.drop_rate = 0;
-            self.board.stamp(self.current.imprint(), self.position);
-            if !self.check_lines() {
-                self.status = Status::Placing(self.current, x, y);
-            } else {
-                self.remaining -= self.lines.len() as i32;
-                while self.remaining <= 0 {
-                    self.remaining += ADVANCE_SPEED;
-                    if self.speed > 1 {
-                        self.speed -= 1;
-                    }
+        let moved = self.move_piece(x, y + 1);
+        if moved {
+            self.lock.fell(y + 1);
+        }
+        moved
+    }
+
+    fn lock_piece(&mut self) {
+        let (x, y) = self.position;
+        self.points += self.drop_rate;
+        self.drop_rate = 0;
+        self.board.stamp(self.current.imprint(), self.position);
+        self.stats.add(self.current.stat(), 1);
+        self.events.push(Event::PieceLocked);
+        if !self.check_lines() {
+            self.status = Status::Placing(self.current, x, y, self.config.are);
+        } else {
+            self.remaining -= self.lines.len() as i32;
+            while self.remaining <= 0 {
+                self.remaining += ADVANCE_SPEED;
+                if self.level < MAX_LEVEL - 1 {
+                    self.level += 1;
+                    self.events.push(Event::LevelUp(self.level));
                 }
-                self.status = Status::Clearing(self.lines.len() as i32 * 3);
             }
+            self.status = Status::Clearing(self.lines.len() as i32 * 3);
+        }
+    }
+
+    // put the piece in play aside, bringing in the one put aside before or,
+    // failing that, the next.
+    fn hold(&mut self) {
+        let p = self.config.rotation.spawn(self.current);
+        match self.held.replace(p) {
+            Some(held) => self.spawn(held),
+            None => self.new_piece(),
         }
+        self.hold_used = true;
     }
 
-    fn left(&mut self) {
+    fn left(&mut self) -> bool {
         let (x, y) = self.position;
-        self.move_piece(x - 1, y);
+        let moved = self.move_piece(x - 1, y);
+        if moved {
+            self.moved();
+        }
+        moved
     }
 
-    fn right(&mut self) {
+    fn right(&mut self) -> bool {
         let (x, y) = self.position;
-        self.move_piece(x + 1, y);
+        let moved = self.move_piece(x + 1, y);
+        if moved {
+            self.moved();
+        }
+        moved
     }
 }
 
 impl<'a> Game for Tetris<'a> {
+    type Config = Config;
     type CellData = ();
 
     fn current_level(&self) -> u32 {
-        MAX_LEVEL - self.speed
+        self.level
     }
     fn score(&self) -> u32 {
         self.points
     }
     fn top_score(&self) -> u32 {
-        self.score_table.get_top_score(&self.config)
+        self.scores.get_top_score(self.config.level, self.config.btype)
     }
     fn board(&self) -> &Imprint<()> {
         &self.board
     }
-    fn next(&self) -> Option<&Imprint<()>> {
+    fn next(&self) -> Vec<&Imprint<()>> {
         match self.status {
-            Status::Menu(_) => None,
-            _ => Some(self.next.imprint()),
+            Status::Menu(_) => Vec::new(),
+            _ => self.next.iter().map(|p| p.imprint()).collect(),
         }
     }
     fn tick(&mut self) -> TickResult {
+        self.events.clear();
+        self.achievements.tick();
+        if self.scores.is_open() {
+            self.scores.tick(&mut self.input);
+            return TickResult::Continue;
+        }
+        if self.stats.is_open() {
+            self.stats.tick(&mut self.input);
+            return TickResult::Continue;
+        }
+        if matches!(self.status, Status::Active | Status::Clearing(_) | Status::Placing(..)) {
+            self.stats.add(stats::TIME, 1);
+        }
         match self.status {
             Status::Active => {
                 if self.input.escape {
                     self.status = Status::Paused;
                     self.input.escape = false;
                 } else {
-                    if self.input.left {
-                        if self.input.skip == 0 || self.input.skip > KEY_DELAY {
-                            self.left();
-                        }
-                        if self.input.skip <= KEY_DELAY {
-                            self.input.skip += 1;
-                        }
-                    } else if self.input.right {
-                        if self.input.skip == 0 || self.input.skip > KEY_DELAY {
-                            self.right();
-                        }
-                        if self.input.skip <= KEY_DELAY {
-                            self.input.skip += 1;
+                    let shift = self.input.shift;
+                    for _ in 0..shift.unsigned_abs() {
+                        let moved = if shift < 0 { self.left() } else { self.right() };
+                        if !moved {
+                            break;
                         }
                     }
                     if self.input.button_b {
@@ -250,13 +675,42 @@ impl<'a> Game for Tetris<'a> {
                         self.input.drop = false;
                         self.hard_drop()
                     } else if self.input.down {
-                        self.drop_rate += 1;
-                        self.down()
+                        // pushed down onto something, a classic piece locks
+                        // straight away; others wait as if they had fallen.
+                        for _ in 0..self.input.fall {
+                            if !self.down() {
+                                if self.config.lock.locks_at_once() {
+                                    self.drop_rate += 1;
+                                    self.lock_piece();
+                                }
+                                break;
+                            }
+                            self.drop_rate += 1;
+                        }
                     } else {
                         self.drop_rate = 0;
-                        self.gravity_tick = (self.gravity_tick + 1) % self.speed;
-                        if self.gravity_tick == 0 {
-                            self.down()
+                        // a piece that lands part way through a tick's fall
+                        // only locks when it is next due to fall, so that
+                        // even the fastest levels leave a tick to slide it.
+                        for i in 0..self.gravity.advance(gravity(self.level)) {
+                            if !self.down() {
+                                if i == 0 && self.config.lock.locks_at_once() {
+                                    self.lock_piece();
+                                }
+                                break;
+                            }
+                        }
+                    }
+                    if self.status == Status::Active && self.grounded() &&
+                        self.lock.rest(self.config.lock)
+                    {
+                        self.lock_piece();
+                    }
+                    // not once the piece has landed.
+                    if self.input.hold {
+                        self.input.hold = false;
+                        if self.config.hold && !self.hold_used && self.status == Status::Active {
+                            self.hold();
                         }
                     }
                 }
@@ -268,6 +722,7 @@ impl<'a> Game for Tetris<'a> {
                     self.status = Status::Active;
                 } else if self.input.escape {
                     self.input.escape = false;
+                    self.events.push(Event::GameOver);
                     self.status = Status::Raising(self.board.size().1);
                 }
             }
@@ -301,14 +756,13 @@ impl<'a> Game for Tetris<'a> {
                     self.input.escape = false;
                     return TickResult::PrevGame;
                 }
-                if self.input.right && self.config.btype < MAX_BTYPE {
-                    self.input.right = false;
+                // a step a tick, however fast the directions repeat.
+                if self.input.shift > 0 && self.config.btype < MAX_BTYPE {
                     let top = self.board.size().1 - 1 - self.config.btype as usize;
-                    self.board.random_line(top, Cell::Filled(()));
+                    self.board.random_line(top, Cell::Filled(()), &mut self.rng);
                     self.config.btype += 1;
                 }
-                if self.input.left && self.config.btype > 0 {
-                    self.input.left = false;
+                if self.input.shift < 0 && self.config.btype > 0 {
                     self.config.btype -= 1;
                     let top = self.board.size().1 - 1 - self.config.btype as usize;
                     self.board.clear_line(top);
@@ -316,23 +770,46 @@ impl<'a> Game for Tetris<'a> {
                 if self.input.drop {
                     self.input.drop = false;
                     self.status = Status::Active;
+                    self.stats.add(stats::GAMES, 1);
                 }
-                if self.input.up {
-                    self.input.up = false;
-                    if self.config.level < MAX_LEVEL - 1 {
-                        self.config.level += 1;
-                        self.speed = MAX_LEVEL - self.config.level;
-                        self.remaining = (self.config.level + 1) as i32 * ADVANCE_SPEED;
-                    }
+                if self.input.stats {
+                    self.input.stats = false;
+                    self.stats.show();
                 }
-                if self.input.down {
-                    self.input.down = false;
-                    if self.config.level > 0 {
-                        self.config.level -= 1;
-                        self.speed = MAX_LEVEL - self.config.level;
-                        self.remaining = (self.config.level + 1) as i32 * ADVANCE_SPEED;
+                if self.input.hold {
+                    self.input.hold = false;
+                    self.config.hold = !self.config.hold;
+                }
+                if self.input.mode {
+                    self.input.mode = false;
+                    self.config.randomizer = self.config.randomizer.cycle();
+                    // the pieces to come are dealt again, the new way.
+                    self.next.clear();
+                    self.dealer = Dealer::new(self.config.randomizer);
+                    self.new_piece();
+                }
+                if self.input.button_b {
+                    self.input.button_b = false;
+                    self.scores.browse(self.config.level, self.config.btype);
+                }
+                if self.input.button_a {
+                    self.input.button_a = false;
+                    if self.saved.exists() {
+                        if let Err(e) = self.resume_game() {
+                            eprintln!("could not resume the saved game: {}", e);
+                        }
                     }
                 }
+                if self.input.climb < 0 && self.config.level < MAX_LEVEL - 1 {
+                    self.config.level += 1;
+                    self.level = self.config.level;
+                    self.remaining = (self.config.level + 1) as i32 * ADVANCE_SPEED;
+                }
+                if self.input.climb > 0 && self.config.level > 0 {
+                    self.config.level -= 1;
+                    self.level = self.config.level;
+                    self.remaining = (self.config.level + 1) as i32 * ADVANCE_SPEED;
+                }
             }
             Status::Clearing(0) => {
                 self.clear_lines();
@@ -340,18 +817,61 @@ impl<'a> Game for Tetris<'a> {
                 self.new_piece();
             }
             Status::Clearing(f) => self.status = Status::Clearing(f - 1),
-            Status::Placing(_, _, _) => {
+            Status::Placing(_, _, _, 0) => {
                 self.status = Status::Active;
                 self.new_piece();
             }
+            Status::Placing(p, x, y, f) => self.status = Status::Placing(p, x, y, f - 1),
         }
+        let earned = self.achievements.newly_earned(self, &achievements());
+        self.achievements.earn(&earned);
         TickResult::Continue
     }
     fn is_paused(&self) -> bool {
-        return self.status == Status::Paused;
+        self.status == Status::Paused
     }
 
     fn input_state(&mut self) -> &mut InputState {
         &mut self.input
     }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn configure(&mut self, config: Config) {
+        self.config = config;
+        self.new_game();
+    }
+
+    fn suspend(&mut self) -> io::Result<()> {
+        self.stats.save();
+        match self.status {
+            Status::Menu(_) | Status::Raising(_) | Status::Lowering(_) => Ok(()),
+            _ => self.save_game(),
+        }
+    }
+
+    fn can_resume(&self) -> bool {
+        match self.status {
+            Status::Menu(_) => self.saved.exists(),
+            _ => false,
+        }
+    }
+
+    fn score_table(&self) -> &Leaderboard<'_> {
+        &self.scores
+    }
+
+    fn statistics(&self) -> &Stats<'_> {
+        &self.stats
+    }
+
+    fn achievements(&self) -> &Achievements<'_> {
+        &self.achievements
+    }
+
+    fn events(&self) -> &[Event] {
+        &self.events
+    }
 }
@@ -1,5 +1,9 @@
+use std::io::{self, Read, Write};
+
 use imprint::{Imprint, Cell};
+use persist::{self, Persist};
 use rand::{Rand, Rng};
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Piece {
@@ -22,25 +26,70 @@ pub enum Piece {
     T4,
     Z1,
     Z2,
+    // the other two ways round for I, S and Z, which only the Super
+    // Rotation System turns them to. They come last so that saved games
+    // keep their numbering.
+    I3,
+    I4,
+    S3,
+    S4,
+    Z3,
+    Z4,
+}
+
+// every piece, in declaration order.
+const PIECES: [Piece; 25] = [
+    Piece::I1, Piece::I2, Piece::O1,
+    Piece::J1, Piece::J2, Piece::J3, Piece::J4,
+    Piece::L1, Piece::L2, Piece::L3, Piece::L4,
+    Piece::S1, Piece::S2,
+    Piece::T1, Piece::T2, Piece::T3, Piece::T4,
+    Piece::Z1, Piece::Z2,
+    Piece::I3, Piece::I4,
+    Piece::S3, Piece::S4,
+    Piece::Z3, Piece::Z4,
+];
+
+// the seven shapes, each the way round the Nintendo games bring it in.
+pub const SHAPES: [Piece; 7] = [
+    Piece::I1, Piece::O1, Piece::J1, Piece::L1, Piece::S1, Piece::T1, Piece::Z1,
+];
+
+impl Persist for Piece {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(*self as u8)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Piece> {
+        match PIECES.get(r.read_u8()? as usize) {
+            Some(&p) => Ok(p),
+            None => Err(persist::invalid_data("bad piece")),
+        }
+    }
 }
 
 impl Rand for Piece {
     fn rand<R: Rng>(rng: &mut R) -> Piece {
         let x: u8 = rng.gen_range(0, 7);
-        match x {
-            0 => Piece::I1,
-            1 => Piece::O1,
-            2 => Piece::J1,
-            3 => Piece::L1,
-            4 => Piece::S1,
-            5 => Piece::T1,
-            6 => Piece::Z1,
-            _ => Piece::I2,
-        }
+        SHAPES[x as usize]
     }
 }
 
 impl Piece {
+    // the lifetime statistic counting pieces of this shape, whichever way
+    // round.
+    pub fn stat(&self) -> &'static str {
+        match *self {
+            Piece::I1 | Piece::I2 | Piece::I3 | Piece::I4 => "I PIECES",
+            Piece::O1 => "O PIECES",
+            Piece::J1 | Piece::J2 | Piece::J3 | Piece::J4 => "J PIECES",
+            Piece::L1 | Piece::L2 | Piece::L3 | Piece::L4 => "L PIECES",
+            Piece::S1 | Piece::S2 | Piece::S3 | Piece::S4 => "S PIECES",
+            Piece::T1 | Piece::T2 | Piece::T3 | Piece::T4 => "T PIECES",
+            Piece::Z1 | Piece::Z2 | Piece::Z3 | Piece::Z4 => "Z PIECES",
+        }
+    }
+
     pub fn imprint(&self) -> &Imprint<()> {
         lazy_static! {
             static ref II1: Imprint<()> = Imprint::from_footprint(
@@ -57,6 +106,20 @@ impl Piece {
                   &[0, 0, 1, 0]],
                 Cell::Filled(()),
             );
+            static ref II3: Imprint<()> = Imprint::from_footprint(
+                &[&[0, 0, 0, 0],
+                  &[1, 1, 1, 1],
+                  &[0, 0, 0, 0],
+                  &[0, 0, 0, 0]],
+                Cell::Filled(()),
+            );
+            static ref II4: Imprint<()> = Imprint::from_footprint(
+                &[&[0, 1, 0, 0],
+                  &[0, 1, 0, 0],
+                  &[0, 1, 0, 0],
+                  &[0, 1, 0, 0]],
+                Cell::Filled(()),
+            );
             static ref IS1: Imprint<()> = Imprint::from_footprint(
                 &[&[0, 0, 0],
                   &[0, 1, 1],
@@ -69,6 +132,18 @@ impl Piece {
                   &[0, 0, 1]],
                 Cell::Filled(()),
             );
+            static ref IS3: Imprint<()> = Imprint::from_footprint(
+                &[&[0, 1, 1],
+                  &[1, 1, 0],
+                  &[0, 0, 0]],
+                Cell::Filled(()),
+            );
+            static ref IS4: Imprint<()> = Imprint::from_footprint(
+                &[&[1, 0, 0],
+                  &[1, 1, 0],
+                  &[0, 1, 0]],
+                Cell::Filled(()),
+            );
             static ref IZ1: Imprint<()> = Imprint::from_footprint(
                 &[&[0, 0, 0],
                   &[1, 1, 0],
@@ -81,6 +156,18 @@ impl Piece {
                   &[0, 1, 0]],
                 Cell::Filled(()),
             );
+            static ref IZ3: Imprint<()> = Imprint::from_footprint(
+                &[&[1, 1, 0],
+                  &[0, 1, 1],
+                  &[0, 0, 0]],
+                Cell::Filled(()),
+            );
+            static ref IZ4: Imprint<()> = Imprint::from_footprint(
+                &[&[0, 1, 0],
+                  &[1, 1, 0],
+                  &[1, 0, 0]],
+                Cell::Filled(()),
+            );
             static ref IO1: Imprint<()> = Imprint::from_footprint(
                 &[&[0, 0, 0, 0],
                   &[0, 1, 1, 0],
@@ -164,10 +251,16 @@ impl Piece {
         match *self {
             Piece::I1 => &II1,
             Piece::I2 => &II2,
+            Piece::I3 => &II3,
+            Piece::I4 => &II4,
             Piece::S1 => &IS1,
             Piece::S2 => &IS2,
+            Piece::S3 => &IS3,
+            Piece::S4 => &IS4,
             Piece::Z1 => &IZ1,
             Piece::Z2 => &IZ2,
+            Piece::Z3 => &IZ3,
+            Piece::Z4 => &IZ4,
             Piece::O1 => &IO1,
             Piece::J1 => &IJ1,
             Piece::J2 => &IJ2,
@@ -183,14 +276,67 @@ impl Piece {
             Piece::T4 => &IT4,
         }
     }
-    pub fn rotate_r(&self) -> Piece {
+    // which way round it is, as the Super Rotation System counts them: 0
+    // as the guideline games bring it in, then each quarter turn clockwise.
+    pub fn orientation(&self) -> usize {
         match *self {
-            Piece::I1 => Piece::I2,
+            Piece::O1 |
+            Piece::I3 | Piece::J3 | Piece::L3 | Piece::S3 | Piece::T3 | Piece::Z3 => 0,
+            Piece::I2 | Piece::J4 | Piece::L4 | Piece::S2 | Piece::T4 | Piece::Z2 => 1,
+            Piece::I1 | Piece::J1 | Piece::L1 | Piece::S1 | Piece::T1 | Piece::Z1 => 2,
+            Piece::I4 | Piece::J2 | Piece::L2 | Piece::S4 | Piece::T2 | Piece::Z4 => 3,
+        }
+    }
+
+    pub fn is_i(&self) -> bool {
+        matches!(*self, Piece::I1 | Piece::I2 | Piece::I3 | Piece::I4)
+    }
+
+    // the same shape, turned to the given orientation; O stays as it is.
+    pub fn turned_to(&self, orientation: usize) -> Piece {
+        let mut p = *self;
+        for _ in 0..4 {
+            if p.orientation() == orientation {
+                break;
+            }
+            p = p.turn_r();
+        }
+        p
+    }
+
+    // a quarter turn clockwise through all four ways round, as the Super
+    // Rotation System turns pieces.
+    pub fn turn_r(&self) -> Piece {
+        match *self {
+            Piece::I1 => Piece::I4,
+            Piece::I4 => Piece::I3,
+            Piece::I3 => Piece::I2,
             Piece::I2 => Piece::I1,
-            Piece::S1 => Piece::S2,
+            Piece::S1 => Piece::S4,
+            Piece::S4 => Piece::S3,
+            Piece::S3 => Piece::S2,
             Piece::S2 => Piece::S1,
-            Piece::Z1 => Piece::Z2,
+            Piece::Z1 => Piece::Z4,
+            Piece::Z4 => Piece::Z3,
+            Piece::Z3 => Piece::Z2,
             Piece::Z2 => Piece::Z1,
+            p => p.rotate_r(),
+        }
+    }
+    pub fn turn_l(&self) -> Piece {
+        self.turn_r().turn_r().turn_r()
+    }
+
+    // a quarter turn clockwise as the Nintendo games turn pieces, with I, S
+    // and Z flipping back and forth between two ways round.
+    pub fn rotate_r(&self) -> Piece {
+        match *self {
+            Piece::I1 | Piece::I3 => Piece::I2,
+            Piece::I2 | Piece::I4 => Piece::I1,
+            Piece::S1 | Piece::S3 => Piece::S2,
+            Piece::S2 | Piece::S4 => Piece::S1,
+            Piece::Z1 | Piece::Z3 => Piece::Z2,
+            Piece::Z2 | Piece::Z4 => Piece::Z1,
             Piece::O1 => Piece::O1,
             Piece::J1 => Piece::J2,
             Piece::J2 => Piece::J3,
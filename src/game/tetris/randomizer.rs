@@ -0,0 +1,250 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use rand::Rng;
+
+use persist::{self, Persist};
+use super::Piece;
+use super::piece::SHAPES;
+
+// How the pieces to come are chosen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Randomizer {
+    // each of the seven as likely as any other, every time.
+    Random,
+    // all seven in a shuffled bag, then another bag, as the guideline games
+    // do, so there is never long to wait for any of them.
+    Bag,
+    // as the NES game does: one reroll if the first pick is the piece just
+    // dealt.
+    Nes,
+    // as the first Tetris: The Grand Master does: up to four tries to find
+    // a piece that isn't among the last four.
+    Tgm,
+}
+
+pub const RANDOMIZERS: [Randomizer; 4] =
+    [Randomizer::Random, Randomizer::Bag, Randomizer::Nes, Randomizer::Tgm];
+
+// as the menu and the score table show them, in the same order.
+pub const LABELS: [&str; 4] = ["RANDOM", "7 BAG", "NES", "TGM"];
+
+// how many recent pieces are remembered.
+const HISTORY: usize = 4;
+const TGM_TRIES: usize = 4;
+
+impl Randomizer {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Randomizer::Random => "random",
+            Randomizer::Bag => "bag",
+            Randomizer::Nes => "nes",
+            Randomizer::Tgm => "tgm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Randomizer> {
+        RANDOMIZERS.iter().cloned().find(|r| r.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        LABELS[*self as usize]
+    }
+
+    // the one after it on the menu, going round.
+    pub fn cycle(&self) -> Randomizer {
+        RANDOMIZERS[(*self as usize + 1) % RANDOMIZERS.len()]
+    }
+}
+
+impl Persist for Randomizer {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(*self as u8)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Randomizer> {
+        match RANDOMIZERS.get(r.read_u8()? as usize) {
+            Some(&randomizer) => Ok(randomizer),
+            None => Err(persist::invalid_data("bad randomizer")),
+        }
+    }
+}
+
+// Deals out pieces for one game, with whatever a randomizer needs to
+// remember from one piece to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dealer {
+    randomizer: Randomizer,
+    // what is left of the current bag.
+    bag: Vec<Piece>,
+    // the last few pieces dealt, oldest first.
+    history: Vec<Piece>,
+}
+
+impl Dealer {
+    pub fn new(randomizer: Randomizer) -> Dealer {
+        Dealer {
+            randomizer,
+            bag: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn deal<R: Rng>(&mut self, rng: &mut R) -> Piece {
+        let p = match self.randomizer {
+            Randomizer::Random => rng.gen::<Piece>(),
+            Randomizer::Bag => {
+                if self.bag.is_empty() {
+                    self.bag = SHAPES.to_vec();
+                    rng.shuffle(&mut self.bag);
+                }
+                self.bag.pop().unwrap()
+            }
+            Randomizer::Nes => {
+                // the eighth is a dud, which also rerolls.
+                let first = SHAPES.get(rng.gen_range(0, 8)).cloned();
+                match first {
+                    Some(p) if self.history.last() != Some(&p) => p,
+                    _ => rng.gen::<Piece>(),
+                }
+            }
+            Randomizer::Tgm => {
+                if self.history.is_empty() {
+                    // the first is never S, Z or O, and the game starts as
+                    // if four Zs had come before it.
+                    self.history = vec![Piece::Z1; HISTORY];
+                    let first = [Piece::I1, Piece::J1, Piece::L1, Piece::T1];
+                    first[rng.gen_range(0, first.len())]
+                } else {
+                    let mut p = rng.gen::<Piece>();
+                    for _ in 1..TGM_TRIES {
+                        if !self.history.contains(&p) {
+                            break;
+                        }
+                        p = rng.gen::<Piece>();
+                    }
+                    p
+                }
+            }
+        };
+        self.history.push(p);
+        if self.history.len() > HISTORY {
+            self.history.remove(0);
+        }
+        p
+    }
+}
+
+impl Persist for Dealer {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.randomizer.save(w)?;
+        self.bag.save(w)?;
+        self.history.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Dealer> {
+        Ok(Dealer {
+            randomizer: Randomizer::load(r)?,
+            bag: Vec::load(r)?,
+            history: Vec::load(r)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use random::Random;
+
+    const DEALS: usize = 700;
+
+    fn deals(randomizer: Randomizer, seed: u64) -> Vec<Piece> {
+        let mut dealer = Dealer::new(randomizer);
+        let mut rng = Random::new(seed);
+        (0..DEALS).map(|_| dealer.deal(&mut rng)).collect()
+    }
+
+    #[test]
+    fn bag_deals_every_shape_once_every_seven() {
+        for seed in 0..10 {
+            for bag in deals(Randomizer::Bag, seed).chunks(SHAPES.len()) {
+                for p in &SHAPES {
+                    assert_eq!(bag.iter().filter(|&q| q == p).count(), 1, "{:?}", bag);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_repeats_from_history_only_when_every_try_did() {
+        for seed in 0..10 {
+            let mut dealer = Dealer::new(Randomizer::Tgm);
+            let mut rng = Random::new(seed);
+            let first = dealer.deal(&mut rng);
+            assert!([Piece::I1, Piece::J1, Piece::L1, Piece::T1].contains(&first));
+            let mut history = vec![Piece::Z1, Piece::Z1, Piece::Z1, first];
+            for _ in 0..DEALS {
+                let mut tries = rng;
+                let picks: Vec<Piece> = (0..TGM_TRIES).map(|_| tries.gen()).collect();
+                let expected = picks.iter().cloned()
+                    .find(|p| !history.contains(p))
+                    .unwrap_or(picks[TGM_TRIES - 1]);
+                let p = dealer.deal(&mut rng);
+                assert_eq!(p, expected, "from {:?} with tries {:?}", history, picks);
+                history.remove(0);
+                history.push(p);
+            }
+        }
+    }
+
+    #[test]
+    fn nes_rerolls_a_repeat_once() {
+        for seed in 0..10 {
+            let mut dealer = Dealer::new(Randomizer::Nes);
+            let mut rng = Random::new(seed);
+            let mut last = None;
+            let mut rerolls = 0;
+            for _ in 0..DEALS {
+                let mut tries = rng;
+                let first = SHAPES.get(tries.gen_range(0, 8)).cloned();
+                let p = dealer.deal(&mut rng);
+                if first.is_some() && first != last {
+                    assert_eq!(Some(p), first);
+                } else {
+                    rerolls += 1;
+                    assert_eq!(p, tries.gen::<Piece>());
+                }
+                // nothing more is drawn than the reroll.
+                assert_eq!(rng, tries);
+                last = Some(p);
+            }
+            assert!(rerolls > 0);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_deals_the_same_pieces() {
+        for &randomizer in &RANDOMIZERS {
+            assert_eq!(deals(randomizer, 42), deals(randomizer, 42));
+            assert!(deals(randomizer, 42) != deals(randomizer, 43));
+        }
+    }
+
+    #[test]
+    fn a_saved_dealer_carries_on_where_it_was() {
+        for &randomizer in &RANDOMIZERS {
+            let mut dealer = Dealer::new(randomizer);
+            let mut rng = Random::new(3);
+            for _ in 0..10 {
+                dealer.deal(&mut rng);
+            }
+            let mut bytes = Vec::new();
+            dealer.save(&mut bytes).unwrap();
+            let mut loaded = Dealer::load(&mut &bytes[..]).unwrap();
+            let mut again = rng;
+            for _ in 0..DEALS {
+                assert_eq!(loaded.deal(&mut again), dealer.deal(&mut rng));
+            }
+        }
+    }
+}
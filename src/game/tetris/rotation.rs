@@ -0,0 +1,217 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use persist::{self, Persist};
+use super::Piece;
+
+// How pieces turn, and where they may be nudged to when they don't fit
+// where they are.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Rotation {
+    // as the Nintendo games do: I, S and Z flip back and forth between two
+    // ways round, and a piece with no room to turn stays as it is.
+    Nintendo,
+    // the Super Rotation System of the guideline games, with four ways
+    // round for every piece and wall kicks.
+    Super,
+}
+
+pub const ROTATIONS: [Rotation; 2] = [Rotation::Nintendo, Rotation::Super];
+
+// the places to try a turned piece at, in order, relative to where it was.
+// As in the guideline, y counts up.
+type Kicks = [(i32, i32); 5];
+
+// for J, L, S, T and Z, and O, which always fits at the first. Each turn
+// from an orientation is found at twice it, clockwise, and one after,
+// anticlockwise.
+const KICKS: [Kicks; 8] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0 -> R
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 0 -> L
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 2
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 0
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2 -> L
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 2 -> R
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 0
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 2
+];
+
+const I_KICKS: [Kicks; 8] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // 0 -> R
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // 0 -> L
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // R -> 2
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // R -> 0
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // 2 -> L
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // 2 -> R
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // L -> 0
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // L -> 2
+];
+
+const NO_KICKS: [(i32, i32); 1] = [(0, 0)];
+
+impl Rotation {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Rotation::Nintendo => "nintendo",
+            Rotation::Super => "super",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rotation> {
+        ROTATIONS.iter().cloned().find(|r| r.name() == name)
+    }
+
+    // which way round a piece comes in, whichever way it was before.
+    pub fn spawn(&self, p: Piece) -> Piece {
+        match *self {
+            Rotation::Nintendo => p.turned_to(2),
+            Rotation::Super => p.turned_to(0),
+        }
+    }
+
+    // the piece turned a quarter clockwise, and the places to try it at.
+    pub fn turn_r(&self, p: Piece) -> (Piece, &'static [(i32, i32)]) {
+        match *self {
+            Rotation::Nintendo => (p.rotate_r(), &NO_KICKS),
+            Rotation::Super => (p.turn_r(), Rotation::kicks(p, 0)),
+        }
+    }
+
+    pub fn turn_l(&self, p: Piece) -> (Piece, &'static [(i32, i32)]) {
+        match *self {
+            Rotation::Nintendo => (p.rotate_l(), &NO_KICKS),
+            Rotation::Super => (p.turn_l(), Rotation::kicks(p, 1)),
+        }
+    }
+
+    fn kicks(p: Piece, anticlockwise: usize) -> &'static [(i32, i32)] {
+        let table = if p.is_i() { &I_KICKS } else { &KICKS };
+        &table[p.orientation() * 2 + anticlockwise]
+    }
+}
+
+impl Persist for Rotation {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(*self as u8)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Rotation> {
+        match ROTATIONS.get(r.read_u8()? as usize) {
+            Some(&rotation) => Ok(rotation),
+            None => Err(persist::invalid_data("bad rotation system")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::piece::SHAPES;
+    use imprint::{Cell, Imprint};
+
+    // the piece turned by the Super Rotation System on `board`, and where it
+    // ended up, as the game tries the kicks.
+    fn turn(board: &Imprint<()>, p: Piece, (x, y): (i32, i32), clockwise: bool)
+        -> Option<(Piece, (i32, i32))>
+    {
+        let (q, kicks) = if clockwise {
+            Rotation::Super.turn_r(p)
+        } else {
+            Rotation::Super.turn_l(p)
+        };
+        kicks.iter()
+            .map(|&(dx, dy)| (x + dx, y - dy))
+            .find(|&c| board.accepts(q.imprint(), c))
+            .map(|c| (q, c))
+    }
+
+    #[test]
+    fn i_s_and_z_turn_through_four_ways_round() {
+        for &p in &[Piece::I1, Piece::S1, Piece::Z1] {
+            let mut q = Rotation::Super.spawn(p);
+            let mut seen = Vec::new();
+            for o in 0..4 {
+                assert_eq!(q.orientation(), o);
+                assert!(!seen.contains(&q));
+                seen.push(q);
+                assert_eq!(q.turn_r().turn_l(), q);
+                q = q.turn_r();
+            }
+            assert_eq!(q, seen[0]);
+            // while the Nintendo games only flip them.
+            assert_eq!(p.rotate_r().rotate_r(), p);
+        }
+    }
+
+    #[test]
+    fn every_shape_comes_in_flat_side_down() {
+        for &p in &SHAPES {
+            assert_eq!(Rotation::Super.spawn(p).orientation(), 0);
+        }
+    }
+
+    #[test]
+    fn turning_back_tries_the_same_kicks_the_other_way() {
+        for &p in &[Piece::T1, Piece::T2, Piece::T3, Piece::T4,
+                    Piece::I1, Piece::I2, Piece::I3, Piece::I4] {
+            let (q, there) = Rotation::Super.turn_r(p);
+            let (back, again) = Rotation::Super.turn_l(q);
+            assert_eq!(back, p);
+            let negated: Vec<_> = again.iter().map(|&(x, y)| (-x, -y)).collect();
+            assert_eq!(there, &negated[..], "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn i_kicks_off_either_wall() {
+        let board = Imprint::empty(10, 22);
+        // upright, against the left wall and then the right, each turned
+        // both ways.
+        let cases = [
+            (Piece::I2, -2, true, Piece::I1, 0),
+            (Piece::I2, -2, false, Piece::I3, 0),
+            (Piece::I2, 7, true, Piece::I1, 6),
+            (Piece::I2, 7, false, Piece::I3, 6),
+            (Piece::I4, -1, true, Piece::I3, 0),
+            (Piece::I4, -1, false, Piece::I1, 0),
+            (Piece::I4, 8, true, Piece::I3, 6),
+            (Piece::I4, 8, false, Piece::I1, 6),
+        ];
+        for &(p, x, clockwise, q, kicked) in &cases {
+            assert!(board.accepts(p.imprint(), (x, 10)));
+            assert_eq!(turn(&board, p, (x, 10), clockwise), Some((q, (kicked, 10))), "{:?} at {}", p, x);
+        }
+    }
+
+    #[test]
+    fn t_kicks_down_under_an_overhang_for_a_triple() {
+        let board = Imprint::from_footprint(
+            &[&[0, 0, 0, 0, 0, 0],
+              &[0, 0, 0, 0, 0, 0],
+              &[0, 0, 0, 0, 0, 0],
+              &[0, 0, 0, 1, 1, 1],
+              &[0, 0, 0, 0, 1, 1],
+              &[1, 1, 1, 0, 1, 1],
+              &[1, 1, 0, 0, 1, 1],
+              &[1, 1, 1, 0, 1, 1]],
+            Cell::Filled(()),
+        );
+        // only the last kick, two rows down, fits.
+        assert!(board.accepts(Piece::T3.imprint(), (1, 3)));
+        let (t, (x0, y0)) = turn(&board, Piece::T3, (1, 3), false).unwrap();
+        assert_eq!((t, (x0, y0)), (Piece::T2, (2, 5)));
+        let mut board = board;
+        let imprint = t.imprint();
+        for y in 0..3 {
+            for x in 0..3 {
+                if !imprint[(x, y)].is_empty() {
+                    board[(x0 as usize + x, y0 as usize + y)] = Cell::Filled(());
+                }
+            }
+        }
+        let mut lines = Vec::new();
+        board.full_lines(&mut lines);
+        assert_eq!(lines, vec![5, 6, 7]);
+    }
+}
@@ -1,5 +1,9 @@
+use std::io::{self, Read, Write};
 use std::ops::{Index, IndexMut};
-use rand;
+use rand::Rng;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use persist::{self, Persist};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Cell<A> {
@@ -8,9 +12,26 @@ pub enum Cell<A> {
 }
 impl <A> Cell<A> {
     pub fn is_empty(&self) -> bool {
+        matches!(*self, Cell::Empty)
+    }
+}
+
+impl <A : Persist> Persist for Cell<A> {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
         match *self {
-            Cell::Empty => true,
-            _ => false
+            Cell::Empty => w.write_u8(0),
+            Cell::Filled(ref a) => {
+                w.write_u8(1)?;
+                a.save(w)
+            }
+        }
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Cell<A>> {
+        match r.read_u8()? {
+            0 => Ok(Cell::Empty),
+            1 => Ok(Cell::Filled(A::load(r)?)),
+            _ => Err(persist::invalid_data("bad cell")),
         }
     }
 }
@@ -34,8 +55,8 @@ impl <A : Copy> Imprint<A> {
     pub fn empty(width: usize, height: usize) -> Imprint<A> {
         Imprint {
             footprint: vec![Cell::Empty; width * height],
-            width: width,
-            height: height,
+            width,
+            height,
         }
     }
 
@@ -82,7 +103,7 @@ impl <A : Copy> Imprint<A> {
     }
 
     pub fn size(&self) -> (usize, usize) {
-        return (self.width, self.height);
+        (self.width, self.height)
     }
 
     //clear line without moving others down
@@ -93,12 +114,12 @@ impl <A : Copy> Imprint<A> {
     }
 
     //randomise a line
-    pub fn random_line(&mut self, line: usize, fill_with: Cell<A>) {
+    pub fn random_line<R: Rng>(&mut self, line: usize, fill_with: Cell<A>, rng: &mut R) {
         for x in 0..self.width {
             self[(x,line)] =  fill_with;
         }
         for _ in 0..(self.width / 2) {
-            let x = rand::random::<usize>() % self.width;
+            let x = rng.gen::<usize>() % self.width;
             self[(x, line)] = Cell::Empty;
         }
     }
@@ -132,7 +153,14 @@ impl <A : Copy> Imprint<A> {
                 }
             }
         }
-        return true;
+        true
+    }
+
+    // the first row with anything in it, or the height if there is nothing.
+    pub fn first_row(&self) -> usize {
+        (0..self.height)
+            .find(|&y| (0..self.width).any(|x| !self[(x, y)].is_empty()))
+            .unwrap_or(self.height)
     }
 
     pub fn all_clear(&self, range: usize) -> bool {
@@ -163,13 +191,37 @@ impl <A : Copy> Imprint<A> {
 
 impl <A> Index<(usize, usize)> for Imprint<A> {
     type Output = Cell<A>;
-    fn index<'a>(&'a self, (x, y): (usize, usize)) -> &'a Cell<A> {
+    fn index(&self, (x, y): (usize, usize)) -> &Cell<A> {
         &self.footprint[y * self.width + x]
     }
 }
 
 impl <A> IndexMut<(usize, usize)> for Imprint<A> {
-    fn index_mut<'a>(&'a mut self, (x, y): (usize, usize)) -> &'a mut Cell<A> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Cell<A> {
         &mut self.footprint[y * self.width + x]
     }
 }
+
+impl <A : Copy + Persist> Persist for Imprint<A> {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.width.save(w)?;
+        self.height.save(w)?;
+        for c in &self.footprint {
+            c.save(w)?;
+        }
+        Ok(())
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Imprint<A>> {
+        let width = usize::load(r)?;
+        let height = usize::load(r)?;
+        let len = width.checked_mul(height)
+            .filter(|&len| len <= persist::MAX_LEN as usize)
+            .ok_or_else(|| persist::invalid_data("imprint too large"))?;
+        let mut footprint = Vec::with_capacity(len);
+        for _ in 0..len {
+            footprint.push(Cell::load(r)?);
+        }
+        Ok(Imprint { footprint, width, height })
+    }
+}
@@ -0,0 +1,246 @@
+use std::io::{self, BufRead, Write};
+
+use sdl2::event::Event;
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use sdl2::render::{Canvas, RenderTarget};
+use sdl2::EventPump;
+
+use tetris::drawing::BaseDrawingContext;
+use tetris::game::{Action, ACTIONS};
+use tetris::storage::Storage;
+
+use canvas::CanvasSurface;
+use controls::Controls;
+
+// Opens the key setup screen from any game.
+pub const SETUP_KEY: Keycode = Keycode::F1;
+// Moves on to the next action in the key setup screen.
+const DONE_KEY: Keycode = Keycode::Return;
+
+// Something that can be bound to an action: a key, or a button on a game
+// controller.
+pub trait Binding: Copy + PartialEq {
+    fn from_name(name: &str) -> Option<Self>;
+    fn name(self) -> String;
+    fn defaults() -> Vec<(Self, Action)>;
+}
+
+impl Binding for Keycode {
+    fn from_name(name: &str) -> Option<Keycode> {
+        Keycode::from_name(name)
+    }
+
+    fn name(self) -> String {
+        Keycode::name(self)
+    }
+
+    fn defaults() -> Vec<(Keycode, Action)> {
+        vec![
+            (Keycode::Escape, Action::Escape),
+            (Keycode::Q, Action::Escape),
+            (Keycode::Left, Action::Left),
+            (Keycode::Right, Action::Right),
+            (Keycode::Up, Action::Up),
+            (Keycode::Down, Action::Down),
+            (Keycode::Z, Action::ButtonA),
+            (Keycode::X, Action::ButtonB),
+            (Keycode::Space, Action::Drop),
+            (Keycode::RightBracket, Action::Next),
+            (Keycode::LeftBracket, Action::Prev),
+            (Keycode::Tab, Action::Stats),
+            (Keycode::C, Action::Hold),
+            (Keycode::M, Action::Mode),
+            (Keycode::G, Action::Ghost),
+        ]
+    }
+}
+
+impl Binding for Button {
+    fn from_name(name: &str) -> Option<Button> {
+        Button::from_string(name)
+    }
+
+    fn name(self) -> String {
+        self.string()
+    }
+
+    fn defaults() -> Vec<(Button, Action)> {
+        vec![
+            (Button::Start, Action::Escape),
+            (Button::DPadLeft, Action::Left),
+            (Button::DPadRight, Action::Right),
+            (Button::DPadUp, Action::Up),
+            (Button::DPadDown, Action::Down),
+            (Button::A, Action::ButtonA),
+            (Button::B, Action::ButtonB),
+            (Button::X, Action::Drop),
+            (Button::Y, Action::Hold),
+            (Button::LeftStick, Action::Mode),
+            (Button::RightStick, Action::Ghost),
+            (Button::RightShoulder, Action::Next),
+            (Button::LeftShoulder, Action::Prev),
+            (Button::Back, Action::Stats),
+        ]
+    }
+}
+
+// Which keys or buttons press which actions. They are stored as text, one
+// action per line with the names of its keys, as SDL spells them:
+//
+//     left = Left, A, H
+pub struct Bindings<'a, B: Binding> {
+    keys: Vec<(B, Action)>,
+    storage: &'a dyn Storage,
+    key: &'static str,
+}
+
+impl<'a, B: Binding> Bindings<'a, B> {
+    pub fn defaults(storage: &'a dyn Storage, key: &'static str) -> Bindings<'a, B> {
+        Bindings {
+            keys: B::defaults(),
+            storage,
+            key,
+        }
+    }
+
+    // the default bindings are used if none have been stored yet.
+    pub fn load(storage: &'a dyn Storage, key: &'static str) -> io::Result<Bindings<'a, B>> {
+        let bytes = match storage.read(key)? {
+            Some(bytes) => bytes,
+            None => return Ok(Bindings::defaults(storage, key)),
+        };
+        let mut keys = Vec::new();
+        let mut listed = Vec::new();
+        for (n, line) in bytes.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let action = match Action::from_name(name) {
+                Some(a) => a,
+                None => {
+                    eprintln!("{}:{}: unknown action {}", key, n + 1, name);
+                    continue;
+                }
+            };
+            listed.push(action);
+            for binding in parts.next().unwrap_or("").split(',') {
+                match B::from_name(binding.trim()) {
+                    Some(k) => keys.push((k, action)),
+                    None => eprintln!("{}:{}: unknown key {}", key, n + 1, binding.trim()),
+                }
+            }
+        }
+        // actions added since the file was written get their default keys,
+        // unless something else has them already.
+        for (k, action) in B::defaults() {
+            if !listed.contains(&action) && keys.iter().all(|&(taken, _)| taken != k) {
+                keys.push((k, action));
+            }
+        }
+        Ok(Bindings { keys, storage, key })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut text = Vec::new();
+        writeln!(text, "# action = key, key, ...")?;
+        for &action in ACTIONS.iter() {
+            let names: Vec<String> = self.keys_for(action).iter().map(|&k| k.name()).collect();
+            // an action with no keys is left out, rather than written with
+            // an empty key that can't be read back.
+            if !names.is_empty() {
+                writeln!(text, "{} = {}", action.name(), names.join(", "))?;
+            }
+        }
+        self.storage.write(self.key, &text)
+    }
+
+    pub fn action(&self, key: B) -> Option<Action> {
+        self.keys.iter().find(|&&(k, _)| k == key).map(|&(_, a)| a)
+    }
+
+    pub fn keys_for(&self, action: Action) -> Vec<B> {
+        self.keys.iter().filter(|&&(_, a)| a == action).map(|&(k, _)| k).collect()
+    }
+}
+
+fn label(action: Action) -> &'static str {
+    match action {
+        Action::Escape => "ESCAPE",
+        Action::Left => "LEFT",
+        Action::Right => "RIGHT",
+        Action::Up => "UP",
+        Action::Down => "DOWN",
+        Action::ButtonA => "BUTTON A",
+        Action::ButtonB => "BUTTON B",
+        Action::Drop => "DROP",
+        Action::Next => "NEXT GAME",
+        Action::Prev => "PREV GAME",
+        Action::Stats => "STATS",
+        Action::Hold => "HOLD",
+        Action::Mode => "MODE",
+        Action::Ghost => "GHOST",
+    }
+}
+
+// The key setup screen: asks for the keys and controller buttons of each
+// action in turn, then writes the bindings files. An action keeps its old
+// keys if none are pressed for it, and its old buttons likewise, and
+// pressing the setup key again abandons the changes. Returns false if the
+// window was closed.
+pub fn setup<T: RenderTarget>(
+    controls: &mut Controls<'_>,
+    canvas: &mut Canvas<T>,
+    event_pump: &mut EventPump,
+) -> bool {
+    let mut keys = Vec::new();
+    let mut buttons = Vec::new();
+    for &action in ACTIONS.iter() {
+        let mut pressed = Vec::new();
+        let mut pushed = Vec::new();
+        loop {
+            let viewport = canvas.viewport();
+            let ctx = BaseDrawingContext::new(viewport.width(), viewport.height(), 10, 20, 0);
+            ctx.draw_message(
+                &mut CanvasSurface(canvas),
+                &["KEYS FOR", label(action), "", "PRESS RETURN", "WHEN DONE"],
+            ).unwrap();
+            match event_pump.wait_event() {
+                Event::Quit { .. } => return false,
+                Event::KeyDown { keycode: Some(SETUP_KEY), .. } => return true,
+                Event::KeyDown { keycode: Some(DONE_KEY), .. } => break,
+                Event::KeyDown { keycode: Some(k), repeat: false, .. } if !pressed.contains(&k) => {
+                    pressed.push(k);
+                }
+                Event::ControllerButtonDown { button, .. } if !pushed.contains(&button) => {
+                    pushed.push(button);
+                }
+                // keep track of controllers coming and going meanwhile.
+                e => {
+                    controls.actions(&e);
+                }
+            }
+        }
+        if pressed.is_empty() {
+            pressed = controls.keys.keys_for(action);
+        }
+        if pushed.is_empty() {
+            pushed = controls.buttons.keys_for(action);
+        }
+        keys.extend(pressed.into_iter().map(|k| (k, action)));
+        buttons.extend(pushed.into_iter().map(|b| (b, action)));
+    }
+    controls.keys.keys = keys;
+    if let Err(e) = controls.keys.save() {
+        eprintln!("could not save key bindings to {}: {}", controls.keys.key, e);
+    }
+    controls.buttons.keys = buttons;
+    if let Err(e) = controls.buttons.save() {
+        eprintln!("could not save button bindings to {}: {}", controls.buttons.key, e);
+    }
+    true
+}
@@ -0,0 +1,20 @@
+//! Game logic for the brick games, independent of any windowing or
+//! rendering library.
+
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate byteorder;
+
+pub mod imprint;
+pub mod persist;
+pub mod storage;
+pub mod random;
+pub mod replay;
+pub mod game;
+pub mod drawing;
+pub mod registry;
+
+// The games advance this many times a second, however often the screen is
+// redrawn.
+pub const TICK_RATE: u32 = 20;
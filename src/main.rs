@@ -1,101 +1,163 @@
-#[macro_use]
-extern crate lazy_static;
+extern crate tetris;
 extern crate sdl2;
-extern crate rand;
-extern crate byteorder;
 extern crate app_dirs;
 
-mod imprint;
-mod game;
-mod drawing;
+mod canvas;
+mod cli;
+mod controls;
+mod keys;
 
-use game::tetris::Tetris;
-use game::snake::Snake;
-use game::robots::Robots;
-use drawing::{GameDrawingContext, tetris, snake, robots};
-use game::{Game, TickResult};
+use tetris::game::{self, Action, InputState, TickResult};
+use tetris::TICK_RATE;
+use tetris::persist::Persist;
+use tetris::random::fresh_seed;
+use tetris::replay::{Replay, Playback};
+use tetris::drawing::{Framebuffer, Preferences};
+use tetris::registry::{Cartridge, Entry, Registry};
+use tetris::storage::{FileStorage, MemoryStorage, Recorder, Storage};
+use canvas::CanvasSurface;
+use cli::Args;
+use controls::Controls;
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use app_dirs::{AppDataType, app_root, AppInfo};
 
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::gfx::framerate::FPSManager;
-
-const FRAMERATE: u32 = 20;
 
 use sdl2::render::{RenderTarget, Canvas};
 use sdl2::EventPump;
 
-pub fn game_loop<G: Game, C: GameDrawingContext<G>, T: RenderTarget>(
-    game: &mut G,
-    ctx: &mut C,
+// Where the game loop gets its input from: the keyboard, recording each
+// tick as it goes, or a replay being played back.
+pub enum Session<'a> {
+    Record(&'a mut Replay),
+    Playback(Playback<'a>),
+}
+
+// Told of everything that happens in the game, tick by tick.
+pub type Subscriber<'a> = Box<dyn FnMut(&game::Event) + 'a>;
+
+fn suspend(game: &mut dyn Cartridge) {
+    if let Err(e) = game.suspend() {
+        eprintln!("could not save the game in progress: {}", e);
+    }
+}
+
+pub fn game_loop<T: RenderTarget>(
+    game: &mut dyn Cartridge,
     canvas: &mut Canvas<T>,
     event_pump: &mut EventPump,
+    controls: &mut Controls<'_>,
+    session: &mut Session,
+    subscribers: &mut [Subscriber<'_>],
 ) -> TickResult {
-    let mut rate_limiter = FPSManager::new();
-    rate_limiter.set_framerate(FRAMERATE).unwrap();
+    let tick_length = Duration::from_secs(1) / TICK_RATE;
+    let mut last_frame = Instant::now();
+    let mut lag = Duration::new(0, 0);
     let mut dimensions = (canvas.viewport().width(), canvas.viewport().height());
     loop {
-        for event in event_pump.poll_iter() {
-            let input = game.input_state();
-            match event {
-                Event::Quit { .. } => {
-                    return TickResult::Exit;
+        // the key setup screen needs the event pump to itself.
+        let events: Vec<Event> = event_pump.poll_iter().collect();
+        for event in events {
+            if let Session::Playback(_) = *session {
+                match event {
+                    Event::Quit { .. } => return TickResult::Exit,
+                    e => {
+                        let actions = controls.actions(&e);
+                        if actions.contains(&(Action::Escape, true)) {
+                            return TickResult::Exit;
+                        }
+                        if actions.contains(&(Action::Ghost, true)) {
+                            controls.toggle_ghost();
+                        }
+                        continue;
+                    }
                 }
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => input.escape = true,
-                Event::KeyUp { keycode: Some(Keycode::Escape), .. } => input.escape = false,
-                Event::KeyDown { keycode: Some(Keycode::Q), .. } => input.escape = true,
-                Event::KeyUp { keycode: Some(Keycode::Q), .. } => input.escape = false,
-                Event::KeyDown { keycode: Some(Keycode::Left), .. } => input.left = true,
-                Event::KeyUp { keycode: Some(Keycode::Left), .. } => {
-                    input.left = false;
-                    input.skip = 0;
+            }
+            if let Event::Quit { .. } = event {
+                suspend(game);
+                return TickResult::Exit;
+            }
+            match event {
+                Event::KeyDown { keycode: Some(keys::SETUP_KEY), .. } => {
+                    if !keys::setup(controls, canvas, event_pump) {
+                        suspend(game);
+                        return TickResult::Exit;
+                    }
+                    // keys let go of during setup never reach the game.
+                    *game.input_state() = InputState::new();
+                    // nor does the time spent there.
+                    last_frame = Instant::now();
                 }
-                Event::KeyDown { keycode: Some(Keycode::Right), .. } => input.right = true,
-                Event::KeyUp { keycode: Some(Keycode::Right), .. } => {
-                    input.right = false;
-                    input.skip = 0;
+                e => {
+                    for (action, pressed) in controls.actions(&e) {
+                        // the ghost is the player's to show, not the game's.
+                        if action == Action::Ghost {
+                            if pressed {
+                                controls.toggle_ghost();
+                            }
+                        } else {
+                            game.input_state().set(action, pressed);
+                        }
+                    }
                 }
-                Event::KeyDown { keycode: Some(Keycode::RightBracket), .. } => input.next = true,
-                Event::KeyUp { keycode: Some(Keycode::RightBracket), .. } => input.next = false,
-                Event::KeyDown { keycode: Some(Keycode::LeftBracket), .. } => input.prev = true,
-                Event::KeyUp { keycode: Some(Keycode::LeftBracket), .. } => input.prev = false,
-                Event::KeyDown { keycode: Some(Keycode::Down), .. } => input.down = true,
-                Event::KeyUp { keycode: Some(Keycode::Down), .. } => input.down = false,
-                Event::KeyDown { keycode: Some(Keycode::Up), .. } => input.up = true,
-                Event::KeyUp { keycode: Some(Keycode::Up), .. } => input.up = false,
-                Event::KeyDown { keycode: Some(Keycode::Z), .. } => input.button_a = true,
-                Event::KeyUp { keycode: Some(Keycode::Z), .. } => input.button_a = false,
-                Event::KeyDown { keycode: Some(Keycode::X), .. } => input.button_b = true,
-                Event::KeyUp { keycode: Some(Keycode::X), .. } => input.button_b = false,
-                Event::KeyDown { keycode: Some(Keycode::Space), .. } => input.drop = true,
-                Event::KeyUp { keycode: Some(Keycode::Space), .. } => input.drop = false,
-                _ => {}
             }
         }
-        match game.tick() {
-            TickResult::Continue => {}
-            x => return x,
+        // run as many ticks as the time since the last frame calls for, but
+        // don't try to catch up on more than a second after a stall.
+        let now = Instant::now();
+        lag = (lag + (now - last_frame)).min(Duration::from_secs(1));
+        last_frame = now;
+        while lag >= tick_length {
+            lag -= tick_length;
+            match *session {
+                Session::Record(ref mut replay) => {
+                    controls.repeat.apply(game.input_state());
+                    replay.record(game.input_state())
+                }
+                Session::Playback(ref mut inputs) => match inputs.next() {
+                    Some(input) => *game.input_state() = input,
+                    None => return TickResult::Exit,
+                },
+            }
+            let result = game.tick();
+            for event in game.events() {
+                for subscriber in subscribers.iter_mut() {
+                    subscriber(event);
+                }
+            }
+            // the game left is kept to come back to, with what it counted.
+            if result != TickResult::Continue {
+                if let Session::Record(_) = *session {
+                    suspend(game);
+                }
+                return result;
+            }
         }
         let new_dimensions = (canvas.viewport().width(), canvas.viewport().height());
         if dimensions != new_dimensions {
             dimensions = new_dimensions;
-            ctx.resize(dimensions.0, dimensions.1);
+            game.resize(dimensions.0, dimensions.1);
+        }
+        // presenting waits for the display to refresh, where vsync is
+        // honoured. Nothing changes before the next tick, so wait for that
+        // rather than spin drawing the same frame where it isn't.
+        game.draw(&mut CanvasSurface(canvas), &controls.prefs).unwrap();
+        if let Some(wait) = (last_frame + (tick_length - lag)).checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
         }
-        ctx.draw_game(canvas, &game).unwrap();
-        rate_limiter.delay();
     }
 }
 
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum GameTag {
-    Tetris,
-    Snake,
-    Robots
-}
-
-static GAME_TAGS: [GameTag; 3] = [GameTag::Tetris, GameTag::Snake, GameTag::Robots];
+const WINDOW_SIZE: (u32, u32) = (248, 328);
 
 const APP_INFO: AppInfo = AppInfo {
     name: "Tetris",
@@ -103,60 +165,217 @@ const APP_INFO: AppInfo = AppInfo {
 };
 
 
+// The data directory, or failing that somewhere in memory, so that the games
+// can still be played.
+fn open_storage(dir: Option<&Path>) -> Box<dyn Storage> {
+    let dir = match dir {
+        Some(dir) => fs::create_dir_all(dir).map(|_| dir.to_path_buf()),
+        None => app_root(AppDataType::UserData, &APP_INFO).map_err(io::Error::other),
+    };
+    match dir {
+        Ok(dir) => Box::new(FileStorage::new(&dir)),
+        Err(e) => {
+            eprintln!("could not use the data directory, so nothing will be saved: {}", e);
+            Box::new(MemoryStorage::fallback())
+        }
+    }
+}
+
+// Try the options from the command line on every game before anything is
+// opened, so that one out of range is reported straight away.
+fn check_options(registry: &Registry, options: &[(&str, u32)]) -> Result<(), String> {
+    for entry in registry.entries() {
+        (entry.check)(options)?;
+    }
+    Ok(())
+}
+
 fn play_game<T: RenderTarget>(
-    g: GameTag,
+    entry: &Entry,
+    storage: &dyn Storage,
+    args: &Args,
     canvas: &mut Canvas<T>,
     event_pump: &mut EventPump,
+    controls: &mut Controls<'_>,
+    subscribers: &mut [Subscriber<'_>],
 ) -> TickResult {
-    let mut path = app_root(AppDataType::UserData, &APP_INFO).unwrap();
     let dimensions = (canvas.viewport().width(), canvas.viewport().height());
-    match g {
-        GameTag::Tetris => {
-            path.push("tetris");
-            let mut game = Tetris::new(path.as_path()).unwrap();
-            let mut ctx = tetris::DrawingContext::new(dimensions.0, dimensions.1);
-            game_loop(&mut game, &mut ctx, canvas, event_pump)
-        }
-        GameTag::Snake => {
-            path.push("snake");
-            let mut game = Snake::new(path.as_path()).unwrap();
-            let mut ctx = snake::DrawingContext::new(dimensions.0, dimensions.1);
-            game_loop(&mut game, &mut ctx, canvas, event_pump)
-        }
-        GameTag::Robots => {
-            path.push("robots");
-            let mut game = Robots::new(path.as_path()).unwrap();
-            let mut ctx = robots::DrawingContext::new(dimensions.0, dimensions.1);
-            game_loop(&mut game, &mut ctx, canvas, event_pump)
+    let seed = args.seed.unwrap_or_else(fresh_seed);
+    // the replay keeps what the game found stored, to start from it too.
+    let recorder = Recorder::new(storage);
+    let mut game = (entry.new)(&recorder, entry.save_key, seed, dimensions.0, dimensions.1);
+    let mut replay = game.record(entry.name, seed, &args.options).unwrap();
+    let mut session = Session::Record(&mut replay);
+    let result = game_loop(&mut *game, canvas, event_pump, controls, &mut session, subscribers);
+    drop(game);
+    replay.set_stored(recorder.start());
+    let key = format!("{}.replay", entry.name);
+    let mut bytes = Vec::new();
+    if let Err(e) = replay.save(&mut bytes).and_then(|_| storage.write(&key, &bytes)) {
+        eprintln!("could not save replay to {}: {}", key, e);
+    }
+    result
+}
+
+fn find_game<'a>(registry: &'a Registry, replay: &Replay) -> io::Result<&'a Entry> {
+    registry
+        .find(replay.game())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "replay of an unknown game"))
+}
+
+fn watch_replay<T: RenderTarget>(
+    registry: &Registry,
+    replay: &Replay,
+    canvas: &mut Canvas<T>,
+    event_pump: &mut EventPump,
+    controls: &mut Controls<'_>,
+    subscribers: &mut [Subscriber<'_>],
+) -> io::Result<TickResult> {
+    let entry = find_game(registry, replay)?;
+    // replays start from what was stored when they were recorded, and leave
+    // the player's scores and saved games alone.
+    let storage = MemoryStorage::new();
+    replay.restore(&storage)?;
+    let dimensions = (canvas.viewport().width(), canvas.viewport().height());
+    let mut game = (entry.new)(&storage, entry.save_key, replay.seed(), dimensions.0, dimensions.1);
+    game.play(replay)?;
+    let mut session = Session::Playback(replay.playback());
+    Ok(game_loop(&mut *game, canvas, event_pump, controls, &mut session, subscribers))
+}
+
+// Draw a game to an image file without opening a window: the starting game
+// as it comes up, or the state at the end of a replay.
+fn take_screenshot(
+    registry: &Registry,
+    entry: &Entry,
+    args: &Args,
+    replay: Option<&Replay>,
+    file: &Path,
+) -> io::Result<()> {
+    let entry = match replay {
+        Some(r) => find_game(registry, r)?,
+        None => entry,
+    };
+    let seed = replay.map(|r| r.seed()).or(args.seed).unwrap_or_else(fresh_seed);
+    let (w, h) = args.size.unwrap_or(WINDOW_SIZE);
+    let storage = MemoryStorage::new();
+    if let Some(replay) = replay {
+        replay.restore(&storage)?;
+    }
+    let mut game = (entry.new)(&storage, entry.save_key, seed, w, h);
+    match replay {
+        Some(replay) => {
+            game.run(replay)?;
         }
+        None => game.set_options(&args.options).map_err(io::Error::other)?,
     }
+    let mut fb = Framebuffer::new(w, h);
+    game.draw(&mut fb, &Preferences { ghost: args.ghost }).map_err(io::Error::other)?;
+    fb.save(file)
 }
 
 
 pub fn main() {
+    let registry = Registry::default();
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    let mut current_game = match args.game {
+        None => 0,
+        Some(ref name) => match registry.entries().iter().position(|e| e.name == name.as_str()) {
+            Some(i) => i,
+            None => {
+                let names: Vec<&str> = registry.entries().iter().map(|e| e.name).collect();
+                eprintln!("unknown game {}; the games are {}", name, names.join(", "));
+                process::exit(2);
+            }
+        },
+    };
+    if let Err(e) = check_options(&registry, &args.options) {
+        eprintln!("{}", e);
+        process::exit(2);
+    }
+    let replay = match args.replay {
+        Some(ref file) => match File::open(file).and_then(|mut f| Replay::load(&mut f)) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                eprintln!("could not read replay {}: {}", file.display(), e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if let Some(ref file) = args.screenshot {
+        let entry = &registry.entries()[current_game];
+        if let Err(e) = take_screenshot(&registry, entry, &args, replay.as_ref(), file) {
+            eprintln!("could not save screenshot to {}: {}", file.display(), e);
+            process::exit(1);
+        }
+        return;
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
-    let mut window = video_subsystem
-        .window("Brick Games", 248, 328)
-        .position_centered()
-        .resizable()
-        .opengl()
-        .build()
-        .unwrap();
-    window.set_minimum_size(128, 168).unwrap();
+    let size = args.size.unwrap_or(WINDOW_SIZE);
+    let mut builder = video_subsystem.window("Brick Games", size.0, size.1);
+    builder.position_centered().resizable().opengl();
+    if args.fullscreen {
+        builder.fullscreen_desktop();
+    }
+    let mut window = builder.build().unwrap();
+    window.set_minimum_size(cli::MIN_SIZE.0, cli::MIN_SIZE.1).unwrap();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
-    let mut canvas = window.into_canvas().build().unwrap();
-    let mut current_game: usize = 0;
+    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let storage = open_storage(args.data.as_deref());
+    let mut controls = Controls::new(
+        sdl_context.game_controller().unwrap(),
+        &*storage,
+        "keys.cfg",
+        "pad.cfg",
+        "view.cfg",
+        args.rates,
+    );
+    if args.ghost {
+        controls.prefs.ghost = true;
+    }
+    let mut subscribers: Vec<Subscriber> = Vec::new();
+    if args.print_events {
+        subscribers.push(Box::new(|e| println!("{:?}", e)));
+    }
+    if let Some(replay) = replay {
+        if let Err(e) = watch_replay(&registry, &replay, &mut canvas, &mut event_pump, &mut controls, &mut subscribers) {
+            eprintln!("could not play replay: {}", e);
+        }
+        return;
+    }
     loop {
-        match play_game(GAME_TAGS[current_game], &mut canvas, &mut event_pump) {
-            TickResult::NextGame => current_game = (current_game + 1) % GAME_TAGS.len(),
+        let entry = &registry.entries()[current_game];
+        let result = play_game(
+            entry,
+            &*storage,
+            &args,
+            &mut canvas,
+            &mut event_pump,
+            &mut controls,
+            &mut subscribers,
+        );
+        match result {
+            TickResult::NextGame => current_game = (current_game + 1) % registry.entries().len(),
             TickResult::PrevGame => {
                 if current_game == 0 {
-                    current_game = GAME_TAGS.len() - 1
+                    current_game = registry.entries().len() - 1
                 } else {
-                    current_game = current_game - 1
+                    current_game -= 1
                 }
             }
             _ => break,
@@ -0,0 +1,207 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// Binary encoding for anything written to disk: replays, saved games and
+// the like. Encodings are little-endian, in the same style as the score
+// tables.
+pub trait Persist: Sized {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()>;
+    fn load<R: Read>(r: &mut R) -> io::Result<Self>;
+}
+
+// the most items a stored list may claim to have, so that a corrupt file
+// can't ask for more memory than there is.
+pub const MAX_LEN: u32 = 1 << 20;
+
+pub fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// the length of a list, or of bytes, that is to follow.
+pub fn read_len<R: Read>(r: &mut R) -> io::Result<usize> {
+    let len = r.read_u32::<LittleEndian>()?;
+    if len > MAX_LEN {
+        return Err(invalid_data("stored list is too long"));
+    }
+    Ok(len as usize)
+}
+
+// `len` bytes, however few there turn out to be.
+pub fn read_bytes<R: Read>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated data"));
+    }
+    Ok(buf)
+}
+
+// For something added to the end of a record after records were first
+// written: ones from before it end early, and get `default` instead.
+pub fn load_or<T: Persist, R: Read>(r: &mut R, default: T) -> io::Result<T> {
+    match T::load(r) {
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(default),
+        t => t,
+    }
+}
+
+// Every file starts with a four byte magic number followed by a version.
+pub fn write_header<W: Write>(w: &mut W, magic: &[u8; 4], version: u32) -> io::Result<()> {
+    w.write_all(magic)?;
+    w.write_u32::<LittleEndian>(version)
+}
+
+pub fn read_header<R: Read>(r: &mut R, magic: &[u8; 4]) -> io::Result<u32> {
+    let mut found = [0; 4];
+    r.read_exact(&mut found)?;
+    if &found != magic {
+        return Err(invalid_data("unrecognised file type"));
+    }
+    r.read_u32::<LittleEndian>()
+}
+
+pub fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    w.write_u32::<LittleEndian>(s.len() as u32)?;
+    w.write_all(s.as_bytes())
+}
+
+pub fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    let len = read_len(r)?;
+    let buf = read_bytes(r, len)?;
+    String::from_utf8(buf).map_err(|_| invalid_data("string is not utf-8"))
+}
+
+impl Persist for () {
+    fn save<W: Write>(&self, _: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load<R: Read>(_: &mut R) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Persist for bool {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(*self as u8)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<bool> {
+        match r.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("bad boolean")),
+        }
+    }
+}
+
+impl Persist for u32 {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u32::<LittleEndian>(*self)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<u32> {
+        r.read_u32::<LittleEndian>()
+    }
+}
+
+impl Persist for i32 {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_i32::<LittleEndian>(*self)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<i32> {
+        r.read_i32::<LittleEndian>()
+    }
+}
+
+impl Persist for u64 {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u64::<LittleEndian>(*self)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<u64> {
+        r.read_u64::<LittleEndian>()
+    }
+}
+
+// board coordinates and sizes are small, so usize is stored as a u32.
+impl Persist for usize {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u32::<LittleEndian>(*self as u32)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<usize> {
+        Ok(r.read_u32::<LittleEndian>()? as usize)
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.0.save(w)?;
+        self.1.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<(A, B)> {
+        let a = A::load(r)?;
+        let b = B::load(r)?;
+        Ok((a, b))
+    }
+}
+
+impl<A: Persist> Persist for Option<A> {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            None => w.write_u8(0),
+            Some(ref a) => {
+                w.write_u8(1)?;
+                a.save(w)
+            }
+        }
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Option<A>> {
+        match r.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(A::load(r)?)),
+            _ => Err(invalid_data("bad option")),
+        }
+    }
+}
+
+impl<A: Persist> Persist for Vec<A> {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u32::<LittleEndian>(self.len() as u32)?;
+        for a in self {
+            a.save(w)?;
+        }
+        Ok(())
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Vec<A>> {
+        let len = read_len(r)?;
+        let mut v = Vec::new();
+        for _ in 0..len {
+            v.push(A::load(r)?);
+        }
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_past_the_limit_are_refused() {
+        let mut w = Vec::new();
+        w.write_u32::<LittleEndian>(MAX_LEN).unwrap();
+        w.write_u32::<LittleEndian>(MAX_LEN + 1).unwrap();
+        w.write_u32::<LittleEndian>(u32::MAX).unwrap();
+        let mut r = &w[..];
+        assert_eq!(read_len(&mut r).unwrap(), MAX_LEN as usize);
+        assert_eq!(read_len(&mut r).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read_len(&mut r).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{self, Read, Write};
+
+use rand::{self, Rng, SeedableRng};
+
+use persist::Persist;
+
+// A xorshift64* generator. Its entire state is one word, so a game can be
+// reproduced from its seed, and saved and restored part way through.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random::from_seed(seed)
+    }
+
+    pub fn from_state(state: u64) -> Random {
+        Random { state: if state == 0 { 1 } else { state } }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+// pick a seed for a game that does not need to be reproduced.
+pub fn fresh_seed() -> u64 {
+    rand::random()
+}
+
+impl Rng for Random {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl SeedableRng<u64> for Random {
+    fn reseed(&mut self, seed: u64) {
+        // splitmix64 scramble, so that nearby seeds give unrelated games.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        *self = Random::from_state(z ^ (z >> 31));
+    }
+
+    fn from_seed(seed: u64) -> Random {
+        let mut r = Random { state: 1 };
+        r.reseed(seed);
+        r
+    }
+}
+
+impl Persist for Random {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.state.save(w)
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Random> {
+        Ok(Random::from_state(u64::load(r)?))
+    }
+}
@@ -0,0 +1,168 @@
+use std::io;
+
+use drawing::{self, GameDrawingContext, Preferences, Surface};
+use game::{self, Event, Game, InputState, Options, TickResult};
+use game::robots::{self, Robots};
+use game::snake::{self, Snake};
+use game::tetris::{self, Tetris};
+use replay::Replay;
+use storage::Storage;
+
+// A game together with the context that draws it, with both types hidden so
+// that a front end can treat every game alike.
+pub trait Cartridge {
+    fn input_state(&mut self) -> &mut InputState;
+    fn tick(&mut self) -> TickResult;
+    // what happened during the last tick.
+    fn events(&self) -> &[Event];
+    fn suspend(&mut self) -> io::Result<()>;
+    fn draw(&self, c: &mut dyn Surface, prefs: &Preferences) -> Result<(), String>;
+    fn resize(&mut self, vp_w: u32, vp_h: u32);
+    // change the settings on the game's menu that it has of those given,
+    // and start a fresh game with them.
+    fn set_options(&mut self, options: &[(&str, u32)]) -> Result<(), String>;
+    // start a fresh game as set_options does, and a replay to record it in.
+    fn record(&mut self, name: &str, seed: u64, options: &[(&str, u32)]) -> Result<Replay, String>;
+    // start a fresh game with the settings a replay was recorded with.
+    fn play(&mut self, replay: &Replay) -> io::Result<()>;
+    // play a whole replay through at once, without drawing anything.
+    fn run(&mut self, replay: &Replay) -> io::Result<TickResult>;
+}
+
+pub struct GameCartridge<G: Game, C: GameDrawingContext<G>> {
+    game: G,
+    ctx: C,
+}
+
+impl<G: Game, C: GameDrawingContext<G>> GameCartridge<G, C> {
+    pub fn new(game: G, ctx: C) -> Self {
+        GameCartridge { game, ctx }
+    }
+}
+
+impl<G: Game, C: GameDrawingContext<G>> Cartridge for GameCartridge<G, C> {
+    fn input_state(&mut self) -> &mut InputState {
+        self.game.input_state()
+    }
+
+    fn tick(&mut self) -> TickResult {
+        self.game.tick()
+    }
+
+    fn events(&self) -> &[Event] {
+        self.game.events()
+    }
+
+    fn suspend(&mut self) -> io::Result<()> {
+        self.game.suspend()
+    }
+
+    fn draw(&self, c: &mut dyn Surface, prefs: &Preferences) -> Result<(), String> {
+        self.ctx.draw_game(c, &self.game, prefs)
+    }
+
+    fn resize(&mut self, vp_w: u32, vp_h: u32) {
+        self.ctx.resize(vp_w, vp_h)
+    }
+
+    // the game is configured once, just as a playback will configure it.
+    fn set_options(&mut self, options: &[(&str, u32)]) -> Result<(), String> {
+        let mut config = self.game.config().clone();
+        game::apply_options(&mut config, options)?;
+        self.game.configure(config);
+        Ok(())
+    }
+
+    fn record(&mut self, name: &str, seed: u64, options: &[(&str, u32)]) -> Result<Replay, String> {
+        self.set_options(options)?;
+        Replay::new(name, seed, self.game.config()).map_err(|e| e.to_string())
+    }
+
+    fn play(&mut self, replay: &Replay) -> io::Result<()> {
+        self.game.configure(replay.config()?);
+        Ok(())
+    }
+
+    fn run(&mut self, replay: &Replay) -> io::Result<TickResult> {
+        self.play(replay)?;
+        Ok(replay.run(&mut self.game))
+    }
+}
+
+// Makes a game, given the storage for its scores and saved games and the
+// key to keep them under, the seed for its random numbers and the size of
+// the viewport it is drawn to.
+pub type Constructor =
+    for<'a> fn(&'a dyn Storage, &'a str, u64, u32, u32) -> Box<dyn Cartridge + 'a>;
+
+// Tries options from the command line on a game's settings, without making
+// the game.
+pub type OptionCheck = fn(&[(&str, u32)]) -> Result<(), String>;
+
+pub struct Entry {
+    // identifies the game in replays and on the command line.
+    pub name: &'static str,
+    // the key its scores and saved games are kept under.
+    pub save_key: &'static str,
+    pub new: Constructor,
+    pub check: OptionCheck,
+}
+
+// The games on offer, in the order the next and previous buttons cycle
+// through them.
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut r = Registry::new();
+        r.register("tetris", "tetris", new_tetris, check::<tetris::Config>);
+        r.register("snake", "snake", new_snake, check::<snake::Config>);
+        r.register("robots", "robots", new_robots, check::<robots::Config>);
+        r
+    }
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { entries: Vec::new() }
+    }
+
+    pub fn register(&mut self, name: &'static str, save_key: &'static str, new: Constructor, check: OptionCheck) {
+        self.entries.push(Entry { name, save_key, new, check });
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+}
+
+fn check<C: Options + Default>(options: &[(&str, u32)]) -> Result<(), String> {
+    game::apply_options(&mut C::default(), options)
+}
+
+fn new_tetris<'a>(storage: &'a dyn Storage, key: &'a str, seed: u64, vp_w: u32, vp_h: u32) -> Box<dyn Cartridge + 'a> {
+    Box::new(GameCartridge::new(
+        Tetris::new(storage, key, seed),
+        drawing::tetris::DrawingContext::new(vp_w, vp_h),
+    ))
+}
+
+fn new_snake<'a>(storage: &'a dyn Storage, key: &'a str, seed: u64, vp_w: u32, vp_h: u32) -> Box<dyn Cartridge + 'a> {
+    Box::new(GameCartridge::new(
+        Snake::new(storage, key, seed),
+        drawing::snake::DrawingContext::new(vp_w, vp_h),
+    ))
+}
+
+fn new_robots<'a>(storage: &'a dyn Storage, key: &'a str, seed: u64, vp_w: u32, vp_h: u32) -> Box<dyn Cartridge + 'a> {
+    Box::new(GameCartridge::new(
+        Robots::new(storage, key, seed),
+        drawing::robots::DrawingContext::new(vp_w, vp_h),
+    ))
+}
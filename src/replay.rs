@@ -0,0 +1,179 @@
+use std::io::{self, Read, Write};
+use std::slice;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use game::{self, Game, InputState, TickResult};
+use persist::{self, Persist};
+use storage::Storage;
+
+const MAGIC: &[u8; 4] = b"BGRP";
+const VERSION: u32 = 5;
+
+// A recording of one session of a game: everything needed to construct it
+// again, including what it had stored when the session began, followed by
+// the input state given to every tick. Runs of identical
+// ticks are stored once with a repeat count, which keeps files small since
+// the input rarely changes from one tick to the next.
+pub struct Replay {
+    game: String,
+    seed: u64,
+    config: Vec<u8>,
+    // keys in the game's storage, and what they held.
+    stored: Vec<(String, Vec<u8>)>,
+    inputs: Vec<(u32, InputState)>,
+}
+
+impl Replay {
+    pub fn new<C: Persist>(game: &str, seed: u64, config: &C) -> io::Result<Replay> {
+        let mut bytes = Vec::new();
+        config.save(&mut bytes)?;
+        Ok(Replay {
+            game: game.to_string(),
+            seed,
+            config: bytes,
+            stored: Vec::new(),
+            inputs: Vec::new(),
+        })
+    }
+
+    pub fn game(&self) -> &str {
+        &self.game
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn config<C: Persist>(&self) -> io::Result<C> {
+        C::load(&mut &self.config[..])
+    }
+
+    // what the game had stored when the recording began.
+    pub fn set_stored(&mut self, stored: Vec<(String, Vec<u8>)>) {
+        self.stored = stored;
+    }
+
+    // put back what the game had stored, for it to be constructed on.
+    pub fn restore(&self, storage: &dyn Storage) -> io::Result<()> {
+        for (key, bytes) in &self.stored {
+            storage.write(key, bytes)?;
+        }
+        Ok(())
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.inputs.iter().map(|&(n, _)| n).sum()
+    }
+
+    pub fn record(&mut self, input: &InputState) {
+        if let Some(&mut (ref mut n, ref last)) = self.inputs.last_mut() {
+            if last == input {
+                *n += 1;
+                return;
+            }
+        }
+        self.inputs.push((1, *input));
+    }
+
+    // feed every recorded tick to the game as fast as it will go, stopping
+    // early if the game asks to exit or switch games.
+    pub fn run<G: Game>(&self, game: &mut G) -> TickResult {
+        for input in self.playback() {
+            *game.input_state() = input;
+            match game.tick() {
+                TickResult::Continue => {}
+                x => return x,
+            }
+        }
+        TickResult::Continue
+    }
+
+    pub fn playback(&self) -> Playback<'_> {
+        Playback {
+            runs: self.inputs.iter(),
+            current: (0, InputState::new()),
+        }
+    }
+}
+
+impl Persist for Replay {
+    fn save<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        persist::write_header(w, MAGIC, VERSION)?;
+        persist::write_string(w, &self.game)?;
+        w.write_u64::<LittleEndian>(self.seed)?;
+        w.write_u32::<LittleEndian>(self.config.len() as u32)?;
+        w.write_all(&self.config)?;
+        w.write_u32::<LittleEndian>(self.stored.len() as u32)?;
+        for (key, bytes) in &self.stored {
+            persist::write_string(w, key)?;
+            w.write_u32::<LittleEndian>(bytes.len() as u32)?;
+            w.write_all(bytes)?;
+        }
+        w.write_u32::<LittleEndian>(self.inputs.len() as u32)?;
+        for &(n, ref input) in &self.inputs {
+            w.write_u32::<LittleEndian>(n)?;
+            input.save(w)?;
+        }
+        Ok(())
+    }
+
+    fn load<R: Read>(r: &mut R) -> io::Result<Replay> {
+        // version 2 replays are from before the auto-repeat, and are read
+        // as if they had been recorded with it set the way it was then.
+        // Those before version 4 started from nothing stored, and those
+        // before version 5 had no repeat up and down.
+        let version = persist::read_header(r, MAGIC)?;
+        if !(2..=VERSION).contains(&version) {
+            return Err(persist::invalid_data("unsupported replay version"));
+        }
+        let game = persist::read_string(r)?;
+        let seed = r.read_u64::<LittleEndian>()?;
+        let len = persist::read_len(r)?;
+        let config = persist::read_bytes(r, len)?;
+        let mut stored = Vec::new();
+        if version >= 4 {
+            for _ in 0..persist::read_len(r)? {
+                let key = persist::read_string(r)?;
+                let len = persist::read_len(r)?;
+                stored.push((key, persist::read_bytes(r, len)?));
+            }
+        }
+        let runs = persist::read_len(r)?;
+        let mut inputs = Vec::new();
+        for _ in 0..runs {
+            let n = r.read_u32::<LittleEndian>()?;
+            let input = match version {
+                2 => game::read_v2_input(r)?,
+                3 | 4 => game::read_v3_input(r)?,
+                _ => InputState::load(r)?,
+            };
+            inputs.push((n, input));
+        }
+        Ok(Replay {
+            game,
+            seed,
+            config,
+            stored,
+            inputs,
+        })
+    }
+}
+
+// Yields the recorded input state for each tick in turn.
+pub struct Playback<'a> {
+    runs: slice::Iter<'a, (u32, InputState)>,
+    current: (u32, InputState),
+}
+
+impl<'a> Iterator for Playback<'a> {
+    type Item = InputState;
+
+    fn next(&mut self) -> Option<InputState> {
+        while self.current.0 == 0 {
+            self.current = *self.runs.next()?;
+        }
+        self.current.0 -= 1;
+        Some(self.current.1)
+    }
+}
@@ -0,0 +1,276 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Somewhere to keep score tables, saved games and settings between runs,
+// each under a short name of its own.
+pub trait Storage {
+    // None if nothing is stored under the key.
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+    // replace whatever is stored under the key all at once; if this fails
+    // part way through, the old contents are left as they were.
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()>;
+    // removing something that isn't there is not an error.
+    fn remove(&self, key: &str) -> io::Result<()>;
+    // keep what is under one key under another as well, in place of
+    // whatever was there, all at once; with nothing to copy, the other is
+    // left alone.
+    fn copy(&self, from: &str, to: &str) -> io::Result<()>;
+    // why nothing stored here will last, if that is the case, for the games
+    // to show.
+    fn warning(&self) -> Option<&'static str>;
+}
+
+// Keeps each key as a file in a directory.
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: &Path) -> FileStorage {
+        FileStorage { dir: dir.to_path_buf() }
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // the contents go to a temporary file, which is synced to disk and
+    // renamed over the old one, so a crash or a full disk part way through
+    // doesn't leave half a file behind.
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.dir.join(key);
+        let tmp = temporary(&path);
+        let written = File::create(&tmp).and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        self.rename(&tmp, &path)
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.dir.join(key)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            r => r,
+        }
+    }
+
+    // a second link to the file is renamed into place, so nothing is read
+    // or written. Where links can't be made, it is copied the slow way.
+    fn copy(&self, from: &str, to: &str) -> io::Result<()> {
+        let path = self.dir.join(to);
+        let tmp = temporary(&path);
+        let _ = fs::remove_file(&tmp);
+        match fs::hard_link(self.dir.join(from), &tmp) {
+            Ok(()) => self.rename(&tmp, &path),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(_) => match self.read(from)? {
+                Some(bytes) => self.write(to, &bytes),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn warning(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+impl FileStorage {
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)?;
+        // the rename only lasts once the directory is synced too, on systems
+        // that allow it.
+        if let Ok(dir) = File::open(&self.dir) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    }
+}
+
+// where a file is put together before it takes the place of `path`.
+fn temporary(path: &Path) -> PathBuf {
+    let mut tmp = OsString::from(path.as_os_str());
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
+
+// Keeps everything in memory, to be forgotten when the program exits. Used
+// for replays and screenshots, which shouldn't touch the player's files, and
+// when there is nowhere on disk to keep them.
+pub struct MemoryStorage {
+    files: RefCell<HashMap<String, Vec<u8>>>,
+    warning: Option<&'static str>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage {
+            files: RefCell::new(HashMap::new()),
+            warning: None,
+        }
+    }
+
+    // stands in for storage that couldn't be used, with a warning saying so.
+    pub fn fallback() -> MemoryStorage {
+        MemoryStorage {
+            warning: Some("SAVING OFF"),
+            ..MemoryStorage::new()
+        }
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        MemoryStorage::new()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.files.borrow().get(key).cloned())
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.files.borrow_mut().insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        self.files.borrow_mut().remove(key);
+        Ok(())
+    }
+
+    fn copy(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut files = self.files.borrow_mut();
+        if let Some(bytes) = files.get(from).cloned() {
+            files.insert(to.to_string(), bytes);
+        }
+        Ok(())
+    }
+
+    fn warning(&self) -> Option<&'static str> {
+        self.warning
+    }
+}
+
+fn backup_key(key: &str) -> String {
+    format!("{}.bak", key)
+}
+
+// Write under a key, keeping what was there before as a backup for
+// read_with_backup to go back to. There is always one or the other.
+pub fn write_with_backup(storage: &dyn Storage, key: &str, bytes: &[u8]) -> io::Result<()> {
+    storage.copy(key, &backup_key(key))?;
+    storage.write(key, bytes)
+}
+
+// Read something kept by write_with_backup. If it is missing or `read`
+// rejects it, the backup is used instead and put back in its place. None
+// means neither is there.
+pub fn read_with_backup<T, F>(storage: &dyn Storage, key: &str, read: F) -> io::Result<Option<T>>
+where
+    F: Fn(&[u8]) -> io::Result<T>,
+{
+    let error = match storage.read(key) {
+        Ok(Some(bytes)) => match read(&bytes) {
+            Ok(t) => return Ok(Some(t)),
+            Err(e) => Some(e),
+        },
+        Ok(None) => None,
+        Err(e) => Some(e),
+    };
+    let backup = match storage.read(&backup_key(key)) {
+        Ok(Some(bytes)) => bytes,
+        // with no backup, the original's problem is the one worth reporting.
+        Ok(None) => return error.map_or(Ok(None), Err),
+        Err(e) => return Err(error.unwrap_or(e)),
+    };
+    match read(&backup) {
+        Ok(t) => {
+            if let Some(e) = error {
+                eprintln!("{}: {}; using the backup", key, e);
+            }
+            let _ = storage.write(key, &backup);
+            Ok(Some(t))
+        }
+        Err(e) => Err(error.unwrap_or(e)),
+    }
+}
+
+// Passes everything through to other storage, noting what each key held
+// before it was first used, so that a replay can start from the same place.
+pub struct Recorder<'a> {
+    inner: &'a dyn Storage,
+    start: RefCell<HashMap<String, Option<Vec<u8>>>>,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn new(inner: &'a dyn Storage) -> Recorder<'a> {
+        Recorder {
+            inner,
+            start: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // every key that held something when first used, and what it held.
+    pub fn start(&self) -> Vec<(String, Vec<u8>)> {
+        let mut files: Vec<_> = self
+            .start
+            .borrow()
+            .iter()
+            .filter_map(|(key, bytes)| bytes.clone().map(|b| (key.clone(), b)))
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn touch(&self, key: &str) -> io::Result<()> {
+        if !self.start.borrow().contains_key(key) {
+            let bytes = self.inner.read(key)?;
+            self.start.borrow_mut().insert(key.to_string(), bytes);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Storage for Recorder<'a> {
+    fn read(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let bytes = self.inner.read(key)?;
+        self.start.borrow_mut().entry(key.to_string()).or_insert_with(|| bytes.clone());
+        Ok(bytes)
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.touch(key)?;
+        self.inner.write(key, bytes)
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        self.touch(key)?;
+        self.inner.remove(key)
+    }
+
+    fn copy(&self, from: &str, to: &str) -> io::Result<()> {
+        self.touch(from)?;
+        self.touch(to)?;
+        self.inner.copy(from, to)
+    }
+
+    fn warning(&self) -> Option<&'static str> {
+        self.inner.warning()
+    }
+}
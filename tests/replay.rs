@@ -0,0 +1,160 @@
+// Replays: a recorded session played back from the same seed has to end up
+// where the session did, and files written by older versions still load.
+extern crate byteorder;
+extern crate tetris;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use tetris::drawing::{Framebuffer, Preferences};
+use tetris::game::InputState;
+use tetris::persist::{self, Persist};
+use tetris::registry::{Cartridge, Registry};
+use tetris::replay::Replay;
+use tetris::storage::{MemoryStorage, Recorder, Storage};
+
+const SEED: u64 = 7;
+const SIZE: (u32, u32) = (128, 168);
+const TICKS: u32 = 400;
+
+// something for a player to have done on tick `t`, varied enough to move
+// pieces, the snake and the player about and to start and pause games.
+fn scripted(t: u32) -> InputState {
+    let mut input = InputState::new();
+    input.button_a = t == 0 || t.is_multiple_of(97);
+    input.drop = t.is_multiple_of(31);
+    input.escape = t == 250;
+    input.left = t % 7 < 2;
+    input.right = (3..5).contains(&(t % 11));
+    input.down = t.is_multiple_of(13);
+    input.up = t.is_multiple_of(17);
+    input.shift = input.right as i8 - input.left as i8;
+    input.climb = input.down as i8 - input.up as i8;
+    input.fall = input.down as u8;
+    input
+}
+
+fn screen(game: &dyn Cartridge) -> Vec<u8> {
+    let mut fb = Framebuffer::new(SIZE.0, SIZE.1);
+    game.draw(&mut fb, &Preferences::default()).unwrap();
+    let mut png = Vec::new();
+    fb.write_png(&mut png).unwrap();
+    png
+}
+
+#[test]
+fn recorded_sessions_play_back_the_same() {
+    let registry = Registry::default();
+    for entry in registry.entries() {
+        // a game left suspended earlier, for the recording to resume.
+        let storage = MemoryStorage::new();
+        {
+            let mut game = (entry.new)(&storage, entry.save_key, SEED + 1, SIZE.0, SIZE.1);
+            for t in 0..60 {
+                *game.input_state() = scripted(t);
+                game.tick();
+            }
+            game.suspend().unwrap();
+        }
+
+        let recorder = Recorder::new(&storage);
+        let (mut replay, recorded) = {
+            let mut game = (entry.new)(&recorder, entry.save_key, SEED, SIZE.0, SIZE.1);
+            let mut replay = game.record(entry.name, SEED, &[]).unwrap();
+            for t in 0..TICKS {
+                *game.input_state() = scripted(t);
+                replay.record(game.input_state());
+                game.tick();
+            }
+            (replay, screen(&*game))
+        };
+        replay.set_stored(recorder.start());
+        let mut bytes = Vec::new();
+        replay.save(&mut bytes).unwrap();
+        let replay = Replay::load(&mut &bytes[..]).unwrap();
+        assert_eq!(replay.ticks(), TICKS);
+
+        let storage = MemoryStorage::new();
+        replay.restore(&storage).unwrap();
+        let mut game = (entry.new)(&storage, entry.save_key, replay.seed(), SIZE.0, SIZE.1);
+        game.run(&replay).unwrap();
+        assert!(screen(&*game) == recorded, "{} played back differently", entry.name);
+    }
+}
+
+// the start of a replay file of the given version, for a game whose
+// settings were written as `config`.
+fn header(version: u32, config: &[u8]) -> Vec<u8> {
+    let mut w = Vec::new();
+    persist::write_header(&mut w, b"BGRP", version).unwrap();
+    persist::write_string(&mut w, "tetris").unwrap();
+    w.write_u64::<LittleEndian>(SEED).unwrap();
+    w.write_u32::<LittleEndian>(config.len() as u32).unwrap();
+    w.extend_from_slice(config);
+    w
+}
+
+// bits for the buttons, in the order every version has stored them.
+const LEFT: u16 = 1 << 3;
+const DOWN: u16 = 1 << 1;
+
+#[test]
+fn version_2_replays_load() {
+    let mut w = header(2, &[1, 2, 3]);
+    w.write_u32::<LittleEndian>(2).unwrap();
+    // left held for its first tick, then for a second one.
+    for &(n, skip) in &[(1, 0), (1, 1)] {
+        w.write_u32::<LittleEndian>(n).unwrap();
+        w.write_u16::<LittleEndian>(LEFT | DOWN).unwrap();
+        w.write_u32::<LittleEndian>(skip).unwrap();
+    }
+    let replay = Replay::load(&mut &w[..]).unwrap();
+    assert_eq!(replay.game(), "tetris");
+    assert_eq!(replay.seed(), SEED);
+    let inputs: Vec<_> = replay.playback().map(|i| (i.shift, i.fall, i.climb)).collect();
+    assert_eq!(inputs, vec![(-1, 1, 1), (0, 1, 1)]);
+}
+
+#[test]
+fn version_3_replays_load() {
+    let mut w = header(3, &[]);
+    w.write_u32::<LittleEndian>(1).unwrap();
+    w.write_u32::<LittleEndian>(3).unwrap();
+    w.write_u16::<LittleEndian>(LEFT | DOWN).unwrap();
+    w.write_i8(-1).unwrap();
+    w.write_u8(2).unwrap();
+    let replay = Replay::load(&mut &w[..]).unwrap();
+    assert_eq!(replay.ticks(), 3);
+    for input in replay.playback() {
+        assert!(input.left && input.down);
+        assert_eq!((input.shift, input.fall, input.climb), (-1, 2, 1));
+    }
+}
+
+#[test]
+fn version_4_replays_load() {
+    let mut w = header(4, &[]);
+    w.write_u32::<LittleEndian>(1).unwrap();
+    persist::write_string(&mut w, "scores").unwrap();
+    w.write_u32::<LittleEndian>(2).unwrap();
+    w.extend_from_slice(&[9, 8]);
+    w.write_u32::<LittleEndian>(1).unwrap();
+    w.write_u32::<LittleEndian>(1).unwrap();
+    w.write_u16::<LittleEndian>(0).unwrap();
+    w.write_i8(1).unwrap();
+    w.write_u8(0).unwrap();
+    let replay = Replay::load(&mut &w[..]).unwrap();
+    let storage = MemoryStorage::new();
+    replay.restore(&storage).unwrap();
+    assert_eq!(storage.read("scores").unwrap(), Some(vec![9, 8]));
+    let inputs: Vec<_> = replay.playback().map(|i| (i.shift, i.climb)).collect();
+    assert_eq!(inputs, vec![(1, 0)]);
+}
+
+#[test]
+fn unknown_versions_are_refused() {
+    for &version in &[1, 6] {
+        let mut w = header(version, &[]);
+        w.write_u32::<LittleEndian>(0).unwrap();
+        assert!(Replay::load(&mut &w[..]).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+// Golden images of each game a few seconds into play, drawn headlessly.
+// After a deliberate change to how a game looks, run with GOLDEN_UPDATE set
+// to write the new images, and check them by eye before committing.
+extern crate tetris;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use tetris::drawing::{Framebuffer, Preferences};
+use tetris::registry::Registry;
+use tetris::storage::MemoryStorage;
+
+const SEED: u64 = 1;
+const SIZE: (u32, u32) = (128, 168);
+const TICKS: u32 = 40;
+
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name))
+}
+
+#[test]
+fn screens_match_golden_images() {
+    let registry = Registry::default();
+    for entry in registry.entries() {
+        let storage = MemoryStorage::new();
+        let mut game = (entry.new)(&storage, entry.save_key, SEED, SIZE.0, SIZE.1);
+        // start a game from the menu, then leave it to play on its own.
+        game.input_state().drop = true;
+        game.tick();
+        game.input_state().drop = false;
+        for _ in 0..TICKS {
+            game.tick();
+        }
+        let mut fb = Framebuffer::new(SIZE.0, SIZE.1);
+        game.draw(&mut fb, &Preferences::default()).unwrap();
+        let mut png = Vec::new();
+        fb.write_png(&mut png).unwrap();
+        let path = golden(entry.name);
+        if env::var_os("GOLDEN_UPDATE").is_some() {
+            fs::write(&path, &png).unwrap();
+            continue;
+        }
+        let expected = fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert!(png == expected, "{} no longer looks like {}", entry.name, path.display());
+    }
+}